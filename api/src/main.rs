@@ -107,7 +107,10 @@ fn handle_avatar(
         .chars()
         .take(cmp::min(avatar.length, name.len()))
         .collect();
-    let image = avatar.draw();
+    let image = match avatar.draw() {
+        Ok(value) => value,
+        Err(_) => return handle_bad_request(),
+    };
     let img_path = format!("static/{}.jpg", ext);
 
     image.save(&img_path).unwrap();
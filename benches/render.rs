@@ -0,0 +1,38 @@
+#[macro_use]
+extern crate criterion;
+extern crate initials;
+
+use criterion::Criterion;
+use initials::{AvatarBuilder, Renderer};
+
+const NAMES: [&str; 4] = ["Lucky Seven", "Anakin Skywalker", "王小明", "Ludwig van Beethoven"];
+
+fn avatar(name: &str) -> AvatarBuilder {
+    AvatarBuilder::new(name)
+        .with_font_color("#FFFFFF").unwrap()
+        .with_background_color("#000000").unwrap()
+}
+
+fn builder(c: &mut Criterion) {
+    c.bench_function("builder", |b| {
+        b.iter(|| {
+            for name in &NAMES {
+                avatar(name).draw().unwrap();
+            }
+        })
+    });
+}
+
+fn renderer(c: &mut Criterion) {
+    let renderer = Renderer::new().unwrap();
+    c.bench_function("renderer", |b| {
+        b.iter(|| {
+            for name in &NAMES {
+                renderer.draw(&avatar(name)).unwrap();
+            }
+        })
+    });
+}
+
+criterion_group!(benches, builder, renderer);
+criterion_main!(benches);
@@ -10,6 +10,6 @@ fn avatar() -> AvatarResult {
 
 pub fn main() {
     let avatar = avatar().unwrap();
-    let image = avatar.draw();
+    let image = avatar.draw().unwrap();
     image.save("test.jpg").unwrap();
 }
\ No newline at end of file
@@ -4,6 +4,7 @@ use initials::AvatarBuilder;
 
 pub fn main() {
     let image = AvatarBuilder::new("Anakin Skywalker")
-        .draw();
+        .draw()
+        .unwrap();
     image.save("minimal.jpg").unwrap();
 }
@@ -1,25 +1,46 @@
 //! Avatar module helps to generate avatars according to the initial names.
-use rusttype::{point, Font, Scale};
-use image::{DynamicImage, Rgba, ImageBuffer, imageops};
-use std::io::prelude::*;
-use std::fs::File;
-use std::cmp;
+use rusttype::{point, Point, PositionedGlyph, Scale};
+use image::{Rgba, ImageBuffer, imageops};
+use unicode_segmentation::UnicodeSegmentation;
 use error::Error;
-use color::RgbColor;
+use background::Background;
+use badge::{Badge, BadgeLayer};
+use color::{self, ContrastAlgorithm, RgbColor};
+use effects::{Effects, Glow, Mask, Shadow, Stroke};
+use layer::{BlendMode, Border, Canvas, Layer, LayerContext, LayerPosition, Outline, Overlay, Pattern, Ring, TextLayer};
+use shape::Shape;
+use font::{self, ChainFont, FontData, FontSize, FontSource};
+use renderer::{Coverage, GlyphCache};
+use strategy::InitialsStrategy;
+use script::{DetectScript, Script};
+use std::borrow::Cow;
+use std::iter;
+use std::ops::Range;
+use std::sync::Arc;
+use svg;
+use encode::{self, OutputFormat};
+use icons::IconSet;
+use std::io::Write;
+#[cfg(feature = "shaping")]
+use shaping;
 
 /// Avatar builder that stores the metrics of the image.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AvatarBuilder {
     /// Initials name string
     pub name: String,
-    /// Vectorized font data
-    font_data: Vec<u8>,
-    /// Scale of the font
-    font_scale: Scale,
+    /// Full name the initials are taken from
+    full_name: String,
+    /// Vectorized font data, `None` if no font is bundled
+    font_data: Option<FontData>,
+    /// Data of the fonts tried in order for the characters missing from the primary font
+    fallback_data: Vec<FontData>,
+    /// Size of the font
+    font_size: FontSize,
     /// RGB color of the font
     font_color: RgbColor,
-    /// RGB color of the background
-    background_color: RgbColor,
+    /// Solid color or gradient of the background
+    background: Background,
     /// Size of the inner-text
     pub length: usize,
     /// Width of the avatar
@@ -28,48 +49,147 @@ pub struct AvatarBuilder {
     height: u32,
     /// Contrast ratio for the colors
     contrast_ratio: f32,
+    /// Algorithm to measure the contrast ratio
+    contrast_algorithm: ContrastAlgorithm,
     /// Private property to hold if colors should be randomly generated
     randomized_colors: (bool, bool),
+    /// Generate the random background as a gradient
+    random_gradient: bool,
     /// Gaussian blur of the image
     blur: Option<f32>,
+    /// Stroke, shadow and glow of the text
+    effects: Effects,
+    /// Border inside the edge of the avatar
+    border: Option<Border>,
+    /// Ring around the avatar
+    ring: Option<Ring>,
+    /// Status dot or notification count over the corner of the avatar
+    badge: Option<Badge>,
+    /// Custom layers of the stack with their positions and blend modes
+    layers: Vec<(LayerPosition, Arc<dyn Layer>, BlendMode)>,
+    /// Factor of the pixel sizes to the layout the avatar was built with
+    scale: f32,
+    /// Outline of the avatar
+    shape: Shape,
+    /// Seed for the deterministic color generation
+    seed: Option<u64>,
+    /// Strategy to extract the initials from the full name
+    strategy: Arc<dyn InitialsStrategy>,
+    /// Number of device pixels per layout pixel
+    pixel_ratio: f32,
+    /// Blend the text onto the background in linear light
+    linear_blending: bool,
+    /// Number of samples per axis for the coverage of the shape
+    supersampling: u32,
 }
 
 /// Result type for the avatar generator
 pub type AvatarResult = Result<AvatarBuilder, Error>;
 
+/// RGBA image of the avatar
+type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/// Resolved font color and background of the avatar
+type Colors = (RgbColor, Background);
+
+/// Images of the avatar drawn at several sizes with the resolved colors
+type Drawn = (Vec<Image>, Colors);
+
+/// Maximum number of random colors tried before adjusting the lightness
+const MAX_COLOR_ATTEMPTS: usize = 1000;
+
+/// Number of lightness steps tried towards black and white
+const LIGHTNESS_STEPS: u32 = 64;
+
+/// Angle of the random gradients in degrees, from the top left to the bottom right corner
+const GRADIENT_ANGLE: f32 = 135.;
+
+/// Maximum number of samples per axis for the supersampling
+const MAX_SUPERSAMPLING: u32 = 16;
+
 impl AvatarBuilder {
     /// Construct new AvatarBuilder.
     pub fn new(name: &str) -> AvatarBuilder {
-        // unwrap first chars for the each word and store them
-        // inside the <String>
-        let mut text = String::new();
-        for word in name.split_whitespace() {
-            text.push(word.chars().next().unwrap());
-        }
-
         // default Avatar settings
         AvatarBuilder {
-            name: text.to_uppercase(),
-            font_data: include_bytes!("fonts/Hiragino_Sans_GB_W3.ttf").to_vec(),
-            font_scale: Scale::uniform(150.0),
+            name: DetectScript.initials(name).to_uppercase(),
+            full_name: String::from(name),
+            font_data: FontData::bundled(),
+            fallback_data: Vec::new(),
+            font_size: FontSize::Fixed(150.0),
             length: 2,
             width: 300,
             height: 300,
             randomized_colors: (true, true),
+            random_gradient: false,
             contrast_ratio: 4.5,
+            contrast_algorithm: ContrastAlgorithm::Wcag,
             font_color: RgbColor::new(255, 255, 255), // default white color
-            background_color: RgbColor::new(224, 143, 112), // default background
+            background: Background::Solid(RgbColor::new(224, 143, 112)), // default background
             blur: None,
+            effects: Effects::default(),
+            border: None,
+            ring: None,
+            badge: None,
+            layers: Vec::new(),
+            scale: 1.0,
+            shape: Shape::Square,
+            seed: None,
+            strategy: Arc::new(DetectScript),
+            pixel_ratio: 1.0,
+            linear_blending: false,
+            supersampling: 1,
         }
     }
 
-    /// Change the font of the avatar text. You need to include `.ttf` file.
-    /// Default style is `Hiragino_Sans`.
-    pub fn with_font(mut self, font: &str) -> AvatarResult {
-        let mut f = File::open(font)?;
-        let mut font_data = Vec::new();
-        f.read_to_end(&mut font_data).expect("unable to read data");
-        self.font_data = font_data;
+    /// Change the strategy that extracts the initials from the name.
+    /// Default to `script::DetectScript`, which applies the convention of the detected script
+    /// and takes the first letter of each word for the other scripts.
+    pub fn with_strategy<S: InitialsStrategy + 'static>(mut self, strategy: S) -> AvatarResult {
+        self.name = strategy.initials(&self.full_name).to_uppercase();
+        self.strategy = Arc::new(strategy);
+        Ok(self)
+    }
+
+    /// Override the detected script of the name and apply its initials convention.
+    pub fn with_script(self, script: Script) -> AvatarResult {
+        self.with_strategy(script)
+    }
+
+    /// Change the font of the avatar text. You need to include `.ttf`, `.otf`, `.ttc` or `.otc` file,
+    /// the first face of the collections is used.
+    /// Default style is `Hiragino_Sans`, see the `font-*` features of the crate.
+    pub fn with_font(self, font: &str) -> AvatarResult {
+        self.with_font_source(FontSource::Path(String::from(font)))
+    }
+
+    /// Change the font of the avatar text to the font data, e.g. embedded with `include_bytes!`.
+    pub fn with_font_bytes<D: Into<Arc<[u8]>>>(self, data: D) -> AvatarResult {
+        self.with_font_source(FontSource::Bytes(data.into()))
+    }
+
+    /// Change the font of the avatar text to the installed font with the family name,
+    /// picking the face with the closest weight (`400` regular, `700` bold).
+    pub fn with_system_font(self, family: &str, weight: u16) -> AvatarResult {
+        self.with_font_source(FontSource::System {
+            family: String::from(family),
+            weight,
+        })
+    }
+
+    /// Change the font of the avatar text to the font loaded from the source.
+    pub fn with_font_source(mut self, source: FontSource) -> AvatarResult {
+        self.font_data = Some(source.load()?);
+        Ok(self)
+    }
+
+    /// Add fonts for the characters missing from the primary font.
+    /// Each character is drawn with the first font of the chain that has a glyph for it.
+    pub fn with_font_fallbacks(mut self, fallbacks: Vec<FontSource>) -> AvatarResult {
+        self.fallback_data = fallbacks
+            .into_iter()
+            .map(FontSource::load)
+            .collect::<Result<_, _>>()?;
         Ok(self)
     }
 
@@ -83,17 +203,55 @@ impl AvatarBuilder {
     /// Change the uniform scale of font.
     /// Default to `150.0`.
     pub fn with_font_scale(mut self, scale: f32) -> AvatarResult {
-        self.font_scale = Scale::uniform(scale);
+        if !scale.is_finite() || scale <= 0. {
+            return Err(Error::InvalidFontScale(scale));
+        }
+
+        self.font_size = FontSize::Fixed(scale);
         Ok(self)
     }
 
+    /// Change the size of font. `FontSize::Auto` fits the text into the avatar,
+    /// so names of different lengths look consistent.
+    /// Default to `FontSize::Fixed(150.0)`.
+    pub fn with_font_size(self, size: FontSize) -> AvatarResult {
+        match size {
+            FontSize::Fixed(scale) => self.with_font_scale(scale),
+            FontSize::Auto { max_fill } => {
+                if !(max_fill > 0. && max_fill <= 1.) {
+                    return Err(Error::InvalidFill(max_fill));
+                }
+
+                let mut avatar = self;
+                avatar.font_size = size;
+                Ok(avatar)
+            }
+        }
+    }
+
     /// Change the background color of the avatar. You need to specify hex color code.
     pub fn with_background_color(mut self, color: &str) -> AvatarResult {
-        self.background_color = color.parse()?;
+        self.background = Background::Solid(color.parse()?);
         self.randomized_colors.1 = false;
         Ok(self)
     }
 
+    /// Change the background to a solid color or a gradient, see `Background`.
+    /// Fails if a gradient has no stops or an offset is out of range `[0, 1]`.
+    pub fn with_background(mut self, background: Background) -> AvatarResult {
+        self.background = background.validated()?;
+        self.randomized_colors.1 = false;
+        Ok(self)
+    }
+
+    /// Generate the random background as a two-stop diagonal gradient instead of a solid color.
+    /// Combine with `with_seed` or `with_deterministic_colors` to derive it from the name.
+    pub fn with_random_gradient(mut self) -> AvatarResult {
+        self.random_gradient = true;
+        self.randomized_colors.1 = true;
+        Ok(self)
+    }
+
     /// Change the length of initials characters taken from the name.
     /// Default to `2`. 
     pub fn with_length(mut self, length: usize) -> AvatarResult {
@@ -104,6 +262,10 @@ impl AvatarBuilder {
     /// Change the width of the avatar.
     /// Default to `300`. 
     pub fn with_width(mut self, width: u32) -> AvatarResult {
+        if width == 0 {
+            return Err(Error::InvalidDimensions { width, height: self.height });
+        }
+
         self.width = width;
         Ok(self)
     }
@@ -111,6 +273,10 @@ impl AvatarBuilder {
     /// Change the height of the avatar.
     /// Default to `300`. 
     pub fn with_height(mut self, height: u32) -> AvatarResult {
+        if height == 0 {
+            return Err(Error::InvalidDimensions { width: self.width, height });
+        }
+
         self.height = height;
         Ok(self)
     }
@@ -122,6 +288,14 @@ impl AvatarBuilder {
         Ok(self)
     }
 
+    /// Change the algorithm that measures the contrast ratio.
+    /// Default to `ContrastAlgorithm::Wcag`. With `ContrastAlgorithm::Apca` the ratio is
+    /// the lightness contrast `|Lc|`, so use values like `60.` or `75.`.
+    pub fn with_contrast_algorithm(mut self, algorithm: ContrastAlgorithm) -> AvatarResult {
+        self.contrast_algorithm = algorithm;
+        Ok(self)
+    }
+
 
     /// Apply gaussian blur to the avatar.
     pub fn with_blur(mut self, blur: f32) -> AvatarResult {
@@ -129,99 +303,1452 @@ impl AvatarBuilder {
         Ok(self)
     }
 
+    /// Draw an outline of the width in pixels around the text.
+    pub fn with_text_stroke(mut self, width: f32, color: &str) -> AvatarResult {
+        let color = color.parse()?;
+        self.effects.stroke = Some(Stroke { width: effect_size(width)?, color });
+        Ok(self)
+    }
+
+    /// Draw a shadow of the text moved by the offset in pixels and blurred with
+    /// the gaussian blur of the radius. Unlike `with_blur` the text stays sharp.
+    pub fn with_drop_shadow(mut self, offset: (f32, f32), blur: f32, color: &str) -> AvatarResult {
+        if let Some(&invalid) = [offset.0, offset.1].iter().find(|o| !o.is_finite()) {
+            return Err(Error::InvalidEffectSize(invalid));
+        }
+
+        let color = color.parse()?;
+        let blur = if blur == 0. { 0. } else { effect_size(blur)? };
+        self.effects.shadow = Some(Shadow { offset, blur, color });
+        Ok(self)
+    }
+
+    /// Draw a soft glow of the radius in pixels around the text.
+    pub fn with_outer_glow(mut self, radius: f32, color: &str) -> AvatarResult {
+        let color = color.parse()?;
+        self.effects.glow = Some(Glow { radius: effect_size(radius)?, color });
+        Ok(self)
+    }
+
+    /// Draw a border of the width in pixels inside the edge of the avatar, following its shape.
+    /// The auto-fit text stays inside the border. Drawing fails if the border and the ring
+    /// take half of the avatar, leaving no room inside them.
+    pub fn with_border(mut self, width: f32, color: &str) -> AvatarResult {
+        let color = color.parse()?;
+        self.border = Some(Border { width: border_size(width, false)?, color });
+        Ok(self)
+    }
+
+    /// Draw a ring of the width in pixels along the edge of the avatar, with a transparent gap
+    /// between the ring and the avatar, e.g. to show the presence. The avatar inside the ring
+    /// is shrunk to keep the size of the image.
+    pub fn with_ring(mut self, width: f32, gap: f32, color: &str) -> AvatarResult {
+        let color = color.parse()?;
+        self.ring = Some(Ring { width: border_size(width, false)?, gap: border_size(gap, true)?, color });
+        Ok(self)
+    }
+
+    /// Draw the badge over the corner of the avatar with a transparent gap around it,
+    /// e.g. the online status or the number of the unread messages.
+    pub fn with_badge(mut self, badge: Badge) -> AvatarResult {
+        if !badge.size.is_finite() || badge.size <= 0. {
+            return Err(Error::InvalidBadgeSize(badge.size));
+        }
+        self.badge = Some(badge);
+        Ok(self)
+    }
+
+    /// Add a layer to the stack over the background and under the text or over the text,
+    /// blending it with the layers under it. Layers at the same position are drawn in order.
+    pub fn with_layer<L: Layer + 'static>(mut self, layer: L, position: LayerPosition, mode: BlendMode) -> AvatarResult {
+        self.layers.push((position, Arc::new(layer), mode));
+        Ok(self)
+    }
+
+    /// Draw the pattern over the background and under the text.
+    pub fn with_pattern(self, pattern: Pattern, mode: BlendMode) -> AvatarResult {
+        self.with_layer(pattern, LayerPosition::BelowText, mode)
+    }
+
+    /// Draw the image stretched over the whole avatar, including the text.
+    pub fn with_overlay(self, image: ImageBuffer<Rgba<u8>, Vec<u8>>, mode: BlendMode) -> AvatarResult {
+        self.with_layer(Overlay::new(image), LayerPosition::AboveText, mode)
+    }
+
+    /// Generate the random colors deterministically from the given seed.
+    /// The same seed always produces the same colors on every platform and crate version.
+    pub fn with_seed(mut self, seed: u64) -> AvatarResult {
+        self.seed = Some(seed);
+        Ok(self)
+    }
+
+    /// Generate the random colors deterministically from a stable hash of the full name,
+    /// so the same name always gets the same avatar.
+    pub fn with_deterministic_colors(mut self) -> AvatarResult {
+        self.seed = Some(color::name_seed(&self.full_name));
+        Ok(self)
+    }
+
+    /// Change the shape of the avatar. Pixels outside of the shape are transparent.
+    /// Default to `Shape::Square`.
+    pub fn with_shape(mut self, shape: Shape) -> AvatarResult {
+        self.shape = shape;
+        Ok(self)
+    }
+
+    /// Change the number of device pixels per layout pixel, e.g. `2.0` for retina screens.
+    /// The size of the avatar and everything measured in pixels, from the font scale to
+    /// the badge, are scaled together, so the avatar keeps the proportions of the `1.0` layout.
+    /// SVG documents are not affected. Default to `1.0`.
+    pub fn with_device_pixel_ratio(mut self, ratio: f32) -> AvatarResult {
+        if !ratio.is_finite() || ratio <= 0. {
+            return Err(Error::InvalidPixelRatio(ratio));
+        }
+
+        self.pixel_ratio = ratio;
+        Ok(self)
+    }
+
+    /// Blend the anti-aliased edges of the text onto the background in linear light
+    /// instead of sRGB. Default to `false`.
+    pub fn with_linear_blending(mut self, linear: bool) -> AvatarResult {
+        self.linear_blending = linear;
+        Ok(self)
+    }
+
+    /// Change the number of samples per axis for the anti-aliased edges of the shape,
+    /// in range `[1, 16]`. Default to `1`, the exact coverage of a straight edge.
+    pub fn with_supersampling(mut self, samples: u32) -> AvatarResult {
+        if samples == 0 || samples > MAX_SUPERSAMPLING {
+            return Err(Error::InvalidSupersampling(samples));
+        }
+
+        self.supersampling = samples;
+        Ok(self)
+    }
+
     /// Draw the image according to the metrics given.
-    pub fn draw(self) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
-        // convert font-data vector to rusttype::Font
-        let font = Font::from_bytes(&self.font_data as &[u8]).expect("Error constructing Font");
+    /// Fails if the font is invalid, the text doesn't fit into the image
+    /// or the random colors can't satisfy the contrast ratio.
+    pub fn draw(self) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Error> {
+        let fonts = self.fonts()?;
+        self.render(&fonts, None)
+    }
+
+    /// Draw the image with the parsed fonts, reusing the rasterized glyphs of the cache if given.
+    pub(crate) fn render(
+        &self,
+        fonts: &[ChainFont],
+        cache: Option<&GlyphCache>,
+    ) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Error> {
+        self.render_colors(fonts, cache).map(|(image, _)| image)
+    }
 
-        // substract metrics from the font according to the font scale
-        let v_metrics = font.v_metrics(self.font_scale);
+    /// Draw the image in device pixels like `render`, returns it with the resolved colors.
+    fn render_colors(&self, fonts: &[ChainFont], cache: Option<&GlyphCache>) -> Result<(Image, Colors), Error> {
+        let avatar = self.physical()?;
+        let glyphs = avatar.layout(fonts)?;
+        let colors = avatar.colors(&glyphs)?;
+        Ok((avatar.paint(fonts, glyphs, cache, &colors), colors))
+    }
 
-        // get the number of characters from the given name
-        let text: String = self.name
-            .chars()
-            .take(cmp::min(self.length, self.name.len()))
-            .collect();
+    /// Draw the avatar at each device pixel ratio with the same colors, e.g. `&[1.0, 2.0, 3.0]`.
+    /// The ratios replace the one set with `with_device_pixel_ratio`.
+    pub fn draw_densities(self, ratios: &[f32]) -> Result<Vec<Image>, Error> {
+        let avatars = ratios
+            .iter()
+            .map(|&ratio| self.clone().with_device_pixel_ratio(ratio)?.physical().map(Cow::into_owned))
+            .collect::<Result<_, _>>()?;
+        let (images, _) = self.draw_variants(avatars)?;
+        Ok(images)
+    }
 
-        // layout the glyphs
-        let glyphs: Vec<_> = font
-            .layout(&text, self.font_scale, point(0.0, v_metrics.ascent))
-            .collect();
+    /// Paint the laid out glyphs and the background with the resolved colors.
+    fn paint(
+        &self,
+        fonts: &[ChainFont],
+        glyphs: Vec<(usize, PositionedGlyph)>,
+        cache: Option<&GlyphCache>,
+        &(font_color, ref background): &Colors,
+    ) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        // collect the coverage of the glyphs, the effects are drawn from it
+        let (width, height) = (self.width, self.height);
+        let mask = self.mask(glyphs, cache);
 
-        // substract height/width from the glyphs
-        let glyphs_height = (v_metrics.ascent - v_metrics.descent).ceil() as u32;
-        let glyphs_width = glyphs
+        // composite the stack from the bottom, the text is blended onto the opaque background
+        // so the edges stay opaque
+        let context = LayerContext { width, height, scale: self.scale, font_color, background };
+        let text = TextLayer { mask, effects: self.effects };
+        let mut canvas = Canvas::new(width, height, self.linear_blending);
+        canvas.draw(background, BlendMode::Normal, &context);
+        for &(_, ref layer, mode) in self.layers.iter().filter(|l| l.0 == LayerPosition::BelowText) {
+            canvas.draw(&**layer, mode, &context);
+        }
+        let inset = self.ring.map_or(0., |ring| ring.width + ring.gap);
+        if let Some(border) = self.border {
+            let outline = Outline {
+                shape: self.shape,
+                inset,
+                width: border.width,
+                color: border.color,
+                samples: self.supersampling,
+                clipped: true,
+            };
+            canvas.draw(&outline, BlendMode::Normal, &context);
+        }
+        canvas.draw(&text, BlendMode::Normal, &context);
+        for &(_, ref layer, mode) in self.layers.iter().filter(|l| l.0 == LayerPosition::AboveText) {
+            canvas.draw(&**layer, mode, &context);
+        }
+
+        // cut the canvas according to the shape with anti-aliased edges and draw the ring around it
+        if self.shape != Shape::Square || inset > 0. {
+            let (shape, samples) = (self.shape, self.supersampling);
+            canvas.clip(|x, y| shape.inset_coverage(x, y, width, height, inset, samples));
+        }
+        if let Some(ring) = self.ring {
+            let outline = Outline {
+                shape: self.shape,
+                inset: 0.,
+                width: ring.width,
+                color: ring.color,
+                samples: self.supersampling,
+                clipped: false,
+            };
+            canvas.draw(&outline, BlendMode::Normal, &context);
+        }
+        if let Some(badge) = self.badge.as_ref().and_then(|badge| self.badge_layer(badge, fonts, cache)) {
+            canvas.draw(&badge, BlendMode::Normal, &context);
+        }
+        let image = canvas.into_image();
+
+        // apply gaussian blur to the image if specified
+        if let Some(b) = self.blur {
+            imageops::blur(&image, b)
+        } else {
+            image
+        }
+    }
+
+    /// Collect the coverage of the glyphs into a mask of the size of the avatar.
+    fn mask(&self, glyphs: Vec<(usize, PositionedGlyph)>, cache: Option<&GlyphCache>) -> Mask {
+        let mut mask = Mask::new(self.width, self.height);
+        for (index, glyph) in glyphs {
+            let coverage = match cache {
+                Some(cache) => cache.coverage(index, &glyph),
+                None => Arc::new(Coverage::rasterize(&glyph)),
+            };
+
+            // glyphs may slightly exceed their advance and line height, the mask clips them
+            let origin = glyph.position();
+            for (x, y, alpha) in coverage.pixels(origin.x.floor() as i32, origin.y.floor() as i32) {
+                mask.add(x, y, f32::from(alpha) / 255.);
+            }
+        }
+        mask
+    }
+
+    /// Draw the badge in its corner with the label rasterized from its glyphs.
+    fn badge_layer(&self, badge: &Badge, fonts: &[ChainFont], cache: Option<&GlyphCache>) -> Option<BadgeLayer> {
+        self.badge_layout(badge, fonts).map(|(layer, label)| BadgeLayer {
+            label: label.map(|(_, glyphs)| self.mask(glyphs, cache)),
+            ..layer
+        })
+    }
+
+    /// Lay out the badge in its corner, the label is drawn with the fonts of the avatar
+    /// and centered on the badge by the bounding box of its glyphs.
+    /// Returns the badge without the label, with the text and the glyphs of the label.
+    fn badge_layout<'a>(&self, badge: &Badge, fonts: &[ChainFont<'a>]) -> Option<(BadgeLayer, Option<Label<'a>>)> {
+        if !badge.is_visible() {
+            return None;
+        }
+
+        let text = badge.label();
+        let line = text.as_ref().map(|label| self.line(fonts, label, Scale::uniform(badge.font_scale())));
+        let (center, size) = badge.bounds(line.as_ref().map(|line| line.width), self.shape, self.width, self.height);
+        let glyphs = line.map(|line| {
+            let (top, bottom) = line
+                .glyphs
+                .iter()
+                .filter_map(|(_, glyph)| glyph.pixel_bounding_box())
+                .fold((0, 0), |(top, bottom), bb| (top.min(bb.min.y), bottom.max(bb.max.y)));
+            let (x, y) = (center.0 - line.width / 2., center.1 - (top + bottom) as f32 / 2.);
+
+            let mut glyphs = line.glyphs;
+            for (_, glyph) in &mut glyphs {
+                let position = glyph.position();
+                glyph.set_position(point(position.x + x, position.y + y));
+            }
+            glyphs
+        });
+
+        let layer = BadgeLayer { center, size, color: badge.color(), label: None, samples: self.supersampling };
+        Some((layer, text.zip(glyphs)))
+    }
+
+    /// Encode the avatar in the format and write it to the writer, e.g. a file or a response body.
+    pub fn encode<W: Write>(self, format: OutputFormat, writer: W) -> Result<(), Error> {
+        let draw = || self.render_colors(&self.fonts()?, None);
+        match format {
+            OutputFormat::Png { compression } => encode::png(&draw()?.0, compression, writer),
+            OutputFormat::Jpeg { quality } => {
+                let (image, (_, background)) = draw()?;
+                encode::jpeg(&image, quality, background.average(), writer)
+            }
+            OutputFormat::WebP => encode::webp(&draw()?.0, writer),
+            OutputFormat::Ico { ref sizes } if sizes.is_empty() => Err(Error::NoIconSizes),
+            OutputFormat::Ico { ref sizes } => {
+                let sizes: Vec<_> = sizes.iter().map(|&size| (size, size)).collect();
+                let (images, _) = self.draw_sizes(&sizes)?;
+                encode::ico(&images, writer)
+            }
+        }
+    }
+
+    /// Draw the favicon and the app icons of the avatar for the web, see `IconSet`.
+    pub fn draw_icons(self) -> Result<IconSet, Error> {
+        IconSet::new(&self)
+    }
+
+    /// Draw the avatar again at each size with the same colors, returns the images with the colors.
+    /// The sizes are in device pixels, so the device pixel ratio is not applied.
+    pub(crate) fn draw_sizes(&self, sizes: &[(u32, u32)]) -> Result<Drawn, Error> {
+        let avatars = sizes
+            .iter()
+            .map(|&(width, height)| self.resized(width, height))
+            .collect::<Result<_, _>>()?;
+        self.draw_variants(avatars)
+    }
+
+    /// Draw the copies of the avatar with the fonts and the colors of this one.
+    fn draw_variants(&self, avatars: Vec<AvatarBuilder>) -> Result<Drawn, Error> {
+        let fonts = self.fonts()?;
+        // the colors are resolved once, so every copy gets the same ones
+        let colors = self.colors(&self.layout(&fonts)?)?;
+        let images = avatars
             .iter()
-            .rev()
-            .map(|g| g.position().x as f32 + g.unpositioned().h_metrics().advance_width)
-            .next()
-            .unwrap_or(0.0)
-            .ceil() as u32;
+            .map(|avatar| {
+                let glyphs = avatar.layout(&fonts)?;
+                Ok(avatar.paint(&fonts, glyphs, None, &colors))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok((images, colors))
+    }
+
+    /// Encode the avatar in the format into a byte vector.
+    pub fn encode_to_vec(self, format: OutputFormat) -> Result<Vec<u8>, Error> {
+        let mut data = Vec::new();
+        self.encode(format, &mut data)?;
+        Ok(data)
+    }
+
+    /// Copy the avatar with another size, scaled like `rescaled` so it looks the same.
+    fn resized(&self, width: u32, height: u32) -> AvatarResult {
+        let factor = (width as f32 / self.width as f32).min(height as f32 / self.height as f32);
+        self.rescaled(width, height, factor)
+    }
+
+    /// Copy the avatar in device pixels, borrowing it if the device pixel ratio is `1.0`.
+    fn physical(&self) -> Result<Cow<'_, AvatarBuilder>, Error> {
+        if self.pixel_ratio == 1. {
+            return Ok(Cow::Borrowed(self));
+        }
+
+        let ratio = self.pixel_ratio;
+        let (width, height) = ((self.width as f32 * ratio).round(), (self.height as f32 * ratio).round());
+        self.rescaled(width as u32, height as u32, ratio).map(Cow::Owned)
+    }
+
+    /// Copy the avatar with the size, drawing it at another size or pixel ratio. Everything
+    /// measured in pixels is scaled by the factor: the fixed font scale, the shape, the blur,
+    /// the text effects, the border, the ring and the badge.
+    fn rescaled(&self, width: u32, height: u32, factor: f32) -> AvatarResult {
+        let mut avatar = self.clone().with_width(width)?.with_height(height)?;
+        avatar.pixel_ratio = 1.0;
+        if let FontSize::Fixed(scale) = self.font_size {
+            avatar.font_size = FontSize::Fixed(scale * factor);
+        }
+        avatar.shape = self.shape.scaled(factor);
+        avatar.blur = self.blur.map(|b| b * factor);
+        avatar.effects = self.effects.scaled(factor);
+        avatar.scale = self.scale * factor;
+        avatar.border = self.border.map(|b| Border { width: b.width * factor, ..b });
+        avatar.ring = self.ring.map(|r| Ring { width: r.width * factor, gap: r.gap * factor, ..r });
+        avatar.badge = self.badge.as_ref().map(|b| b.scaled(factor));
+        Ok(avatar)
+    }
+
+    /// Draw the avatar as an SVG document. The initials are emitted as a `<text>` element
+    /// using the family name of the font, so the font must be available on the client.
+    /// The border, the ring and the badge are drawn as SVG elements and the text effects
+    /// as a filter, see `draw_svg_outlined` for the limitations.
+    pub fn draw_svg(self) -> Result<String, Error> {
+        self.svg(|fonts, text, glyphs, scale, color| {
+            // place the baseline where the rasterized glyphs are positioned
+            let (x, y) = glyphs
+                .first()
+                .map(|(_, g)| (g.position().x, g.position().y))
+                .unwrap_or((0., 0.));
+            let families: Vec<_> = fonts.iter().filter_map(|f| f.data.family()).collect();
+            svg::text(text, x, y, &fonts[0].font, &families, scale, color)
+        })
+    }
+
+    /// Draw the avatar as an SVG document with the glyph outlines converted to `<path>` data,
+    /// so no web font is needed to display it. The border, the ring and the badge are drawn
+    /// as SVG elements and the text effects as a filter, whose square dilation gives the stroke
+    /// and the glow sharper corners than the rasterized ones. The colors are blended in sRGB
+    /// even if linear blending is enabled, and the avatars with custom layers, patterns or
+    /// overlays fail with `Error::UnsupportedSvgLayers` as they can only be rasterized.
+    pub fn draw_svg_outlined(self) -> Result<String, Error> {
+        self.svg(|_, _, glyphs, _, color| {
+            let glyphs: Vec<_> = glyphs.iter().map(|(_, g)| g.clone()).collect();
+            svg::outlines(&glyphs, color)
+        })
+    }
+
+    /// Draw the avatar as an SVG document, `text` creates the elements of the laid out text
+    /// and of the badge label from the fonts, the text, its glyphs, the scale and the color.
+    fn svg<F>(&self, text: F) -> Result<String, Error>
+    where
+        F: Fn(&[ChainFont], &str, &[(usize, PositionedGlyph)], Scale, RgbColor) -> String,
+    {
+        if !self.layers.is_empty() {
+            return Err(Error::UnsupportedSvgLayers);
+        }
+
+        let fonts = self.fonts()?;
+        let glyphs = self.layout(&fonts)?;
+        let (font_color, background) = self.colors(&glyphs)?;
+
+        let content = text(&fonts, &self.text(), &glyphs, self.scale(&fonts), font_color);
+        let badge = self.badge.as_ref().and_then(|badge| {
+            let white = RgbColor::new(255, 255, 255);
+            let (layer, label) = self.badge_layout(badge, &fonts)?;
+            let label = label.map_or_else(String::new, |(label, glyphs)| {
+                text(&fonts, &label, &glyphs, Scale::uniform(badge.font_scale()), white)
+            });
+            Some((layer, label))
+        });
+
+        let document = svg::Document {
+            width: self.width,
+            height: self.height,
+            shape: self.shape,
+            background: &background,
+            blur: self.blur,
+            border: self.border,
+            ring: self.ring,
+            effects: self.effects,
+            text: content,
+            badge,
+        };
+        Ok(document.render())
+    }
+
+    /// Convert font-data vectors to rusttype::Font, the primary font comes first
+    fn fonts(&self) -> Result<Vec<ChainFont<'_>>, Error> {
+        let primary = self.font_data.as_ref().ok_or(Error::MissingFont)?;
+        iter::once(primary)
+            .chain(&self.fallback_data)
+            .map(ChainFont::new)
+            .collect()
+    }
+
+    /// Get the number of grapheme clusters from the given name
+    fn text(&self) -> String {
+        self.name
+            .graphemes(true)
+            .take(self.length)
+            .collect()
+    }
+
+    /// Layout the glyphs in the center of the avatar
+    /// The glyphs are paired with the index of their font in the chain.
+    fn layout<'a>(&self, fonts: &[ChainFont<'a>]) -> Result<Vec<(usize, PositionedGlyph<'a>)>, Error> {
+        // the border and the ring have to leave some room for the avatar inside them
+        let inset = self.inset();
+        if 2. * inset >= self.width.min(self.height) as f32 {
+            return Err(Error::InvalidBorderWidth(inset));
+        }
+
+        // layout the glyphs according to the font scale
+        let line = self.line(fonts, &self.text(), self.scale(fonts));
+
+        // substract height/width from the glyphs
+        let glyphs_height = line.ascent - line.descent;
+        let (glyphs_width, glyphs_height) = (line.width.ceil() as u32, glyphs_height.ceil() as u32);
+
+        if glyphs_width > self.width || glyphs_height > self.height {
+            return Err(Error::TextOverflow {
+                text_width: glyphs_width,
+                text_height: glyphs_height,
+                canvas_width: self.width,
+                canvas_height: self.height,
+            });
+        }
 
         // calculate padding for glyphs
         let left_padding = (self.width - glyphs_width) / 2;
         let top_padding = (self.height - glyphs_height) / 2;
 
-        // create dynamic RGBA image
-        let mut image = DynamicImage::new_rgba8(self.width, self.height).to_rgba();
+        // move the glyphs to the center of the avatar
+        let mut glyphs = line.glyphs;
+        for (_, glyph) in &mut glyphs {
+            let position = glyph.position();
+            glyph.set_position(point(
+                position.x + left_padding as f32,
+                position.y + top_padding as f32 + line.ascent,
+            ));
+        }
+
+        Ok(glyphs)
+    }
+
+    /// Resolve the scale of the font according to the font size
+    fn scale(&self, fonts: &[ChainFont]) -> Scale {
+        match self.font_size {
+            FontSize::Fixed(scale) => Scale::uniform(scale),
+            FontSize::Auto { max_fill } => {
+                // measure the text at a reference scale, the size grows linearly with the scale
+                let reference = 100.;
+                let (width, height) = self.text_size(fonts, Scale::uniform(reference));
+                let fit = self.shape.fit_box_inset(width, height, self.width, self.height, self.inset());
 
-        // randomize colors if not being settled
-        let mut colors = self.randomized_colors;
-        let mut background_color = self.background_color;
+                // keep the rounded up size of the text inside the avatar
+                Scale::uniform(reference * fit * max_fill * 0.999)
+            }
+        }
+    }
+
+    /// Width of the ring, the gap and the border inside the edge of the avatar
+    fn inset(&self) -> f32 {
+        let ring = self.ring.map_or(0., |ring| ring.width + ring.gap);
+        ring + self.border.map_or(0., |border| border.width)
+    }
+
+    /// Measure the advance width and line height of the laid out text
+    fn text_size(&self, fonts: &[ChainFont], scale: Scale) -> (f32, f32) {
+        let line = self.line(fonts, &self.text(), scale);
+        (line.width, line.ascent - line.descent)
+    }
+
+    /// Layout the text on a single line with the baseline at zero. The directional runs of
+    /// the line in visual order are split into runs drawn with the same font of the chain,
+    /// the line gets the highest ascent and the lowest descent of the fonts in use,
+    /// so they share a common baseline.
+    fn line<'a>(&self, fonts: &[ChainFont<'a>], text: &str, scale: Scale) -> Line<'a> {
+        let mut runs: Vec<(usize, String, bool)> = Vec::new();
+        for (range, rtl) in visual_runs(text) {
+            let mut font_runs: Vec<(usize, String, bool)> = Vec::new();
+            for grapheme in text[range].graphemes(true) {
+                let index = pick_font(fonts, grapheme, font_runs.last().map(|run| run.0));
+                match font_runs.last_mut() {
+                    Some(run) if run.0 == index => run.1.push_str(grapheme),
+                    _ => font_runs.push((index, String::from(grapheme), rtl)),
+                }
+            }
+
+            // the logically first run of right-to-left text is drawn rightmost
+            if rtl {
+                font_runs.reverse();
+            }
+            runs.extend(font_runs);
+        }
+
+        // the primary font gives the metrics of the empty line
+        let v_metrics = fonts[0].font.v_metrics(scale);
+        let mut line = Line {
+            glyphs: Vec::new(),
+            width: 0.,
+            ascent: if runs.is_empty() { v_metrics.ascent } else { 0. },
+            descent: if runs.is_empty() { v_metrics.descent } else { 0. },
+        };
+
+        for (index, run, rtl) in runs {
+            let v_metrics = fonts[index].font.v_metrics(scale);
+            line.ascent = line.ascent.max(v_metrics.ascent);
+            line.descent = line.descent.min(v_metrics.descent);
+
+            let (glyphs, width) = layout_run(&fonts[index], &run, rtl, scale, point(line.width, 0.));
+            line.glyphs.extend(glyphs.into_iter().map(|glyph| (index, glyph)));
+            line.width += width;
+        }
+
+        line
+    }
+
+    /// Randomize colors if not being settled. The contrast is checked against
+    /// every color of the background that may appear under the glyphs.
+    fn colors(&self, glyphs: &[(usize, PositionedGlyph)]) -> Result<Colors, Error> {
+        let (random_font, random_background) = self.randomized_colors;
         let mut font_color = self.font_color;
-        loop {
-            match colors {
-                (false, false) => break,
-                (_, _) => {
-                    if colors.0 {
-                        font_color = RgbColor::random();
-                    }
+        let mut background = self.background.clone();
 
-                    if colors.1 {
-                        background_color = RgbColor::random();
-                    }
+        // colors given by the user are taken as they are
+        if !random_font && !random_background {
+            return Ok((font_color, background));
+        }
+
+        // draw the colors from the seed if deterministic colors are requested
+        let mut seed = self.seed;
+        let mut random = || match seed {
+            Some(ref mut state) => RgbColor::seeded(state),
+            None => RgbColor::random(),
+        };
+
+        let region = self.text_region(glyphs);
+        for _ in 0..MAX_COLOR_ATTEMPTS {
+            if random_font {
+                font_color = random();
+            }
+
+            if random_background {
+                background = if self.random_gradient {
+                    let start = random();
+                    let end = start.mix(&random(), 0.5);
+                    Background::LinearGradient { angle: GRADIENT_ANGLE, stops: vec![(0., start), (1., end)] }
+                } else {
+                    Background::Solid(random())
+                };
+            }
+
+            // match if contrast ratio between colors is as expected
+            if self.has_contrast(&font_color, &background, region) {
+                return Ok((font_color, background));
+            }
+        }
 
-                    colors = match font_color.find_ratio(&background_color) {
-                        // match if contrast ratio between colors is as expected
-                        r if r > self.contrast_ratio || r < 1. / self.contrast_ratio => (false, false),
-                        _ => {
-                            if colors.0 | colors.1 {
-                                colors
-                            } else {
-                                (false, true)
-                            }
-                        }
+        // fall back to adjusting the lightness of the last random color
+        for step in 1..=LIGHTNESS_STEPS {
+            let amount = step as f32 / LIGHTNESS_STEPS as f32;
+            for target in &[RgbColor::new(0, 0, 0), RgbColor::new(255, 255, 255)] {
+                if random_background {
+                    let adjusted = background.mix(target, amount);
+                    if self.has_contrast(&font_color, &adjusted, region) {
+                        return Ok((font_color, adjusted));
                     }
-                },
+                } else {
+                    let adjusted = font_color.mix(target, amount);
+                    if self.has_contrast(&adjusted, &background, region) {
+                        return Ok((adjusted, background));
+                    }
+                }
             }
         }
 
+        Err(Error::UnsatisfiableContrast { ratio: self.contrast_ratio })
+    }
+
+    /// Check if the contrast between the font color and the worst background color
+    /// inside the region satisfies the contrast ratio
+    fn has_contrast(&self, font_color: &RgbColor, background: &Background, region: (f32, f32, f32, f32)) -> bool {
+        background
+            .colors_in(region, self.width, self.height)
+            .iter()
+            .all(|color| self.contrast_algorithm.contrast(font_color, color) >= self.contrast_ratio)
+    }
+
+    /// Bounding box of the glyphs as `(left, top, right, bottom)`, the whole avatar if there are none.
+    fn text_region(&self, glyphs: &[(usize, PositionedGlyph)]) -> (f32, f32, f32, f32) {
+        glyphs
+            .iter()
+            .filter_map(|(_, g)| g.pixel_bounding_box())
+            .map(|bb| (bb.min.x as f32, bb.min.y as f32, bb.max.x as f32, bb.max.y as f32))
+            .fold(None, |region: Option<(f32, f32, f32, f32)>, bb| {
+                Some(match region {
+                    Some((l, t, r, b)) => (l.min(bb.0), t.min(bb.1), r.max(bb.2), b.max(bb.3)),
+                    None => bb,
+                })
+            })
+            .unwrap_or((0., 0., self.width as f32, self.height as f32))
+    }
+}
+
+/// Check the size of the effect is a positive number.
+fn effect_size(size: f32) -> Result<f32, Error> {
+    if !size.is_finite() || size <= 0. {
+        return Err(Error::InvalidEffectSize(size));
+    }
+    Ok(size)
+}
+
+/// Check the width of the border or the ring is a positive number, or zero if allowed.
+fn border_size(size: f32, zero: bool) -> Result<f32, Error> {
+    if !size.is_finite() || size < 0. || (size == 0. && !zero) {
+        return Err(Error::InvalidBorderWidth(size));
+    }
+    Ok(size)
+}
+
+/// Split the text into the directional runs in visual order, flagged if they're right-to-left.
+#[cfg(feature = "shaping")]
+fn visual_runs(text: &str) -> Vec<(Range<usize>, bool)> {
+    shaping::visual_runs(text)
+}
+
+/// Without the shaper the text is laid out left-to-right in logical order.
+#[cfg(not(feature = "shaping"))]
+fn visual_runs(text: &str) -> Vec<(Range<usize>, bool)> {
+    vec![(0..text.len(), false)]
+}
+
+/// Layout the text of a single direction with a single font starting from the pen position,
+/// returns the glyphs with the advance width of the run
+#[cfg_attr(not(feature = "shaping"), allow(unused_variables))]
+fn layout_run<'a>(
+    font: &ChainFont<'a>,
+    text: &str,
+    rtl: bool,
+    scale: Scale,
+    start: Point<f32>,
+) -> (Vec<PositionedGlyph<'a>>, f32) {
+    // shape the complex scripts when the shaper is able to read the font
+    #[cfg(feature = "shaping")]
+    {
+        if let Some(run) = shaping::shape(&font.font, &font.data, text, rtl, scale, start) {
+            return run;
+        }
+    }
+
+    let glyphs: Vec<_> = font.font.layout(text, scale, start).collect();
+    let width = glyphs
+        .last()
+        .map(|g| g.position().x + g.unpositioned().h_metrics().advance_width - start.x)
+        .unwrap_or(0.0);
+
+    (glyphs, width)
+}
+
+impl<'a> From<&'a str> for AvatarBuilder {
+    fn from(name: &'a str) -> AvatarBuilder {
+        AvatarBuilder::new(name)
+    }
+}
+
+/// Text of the badge label with its positioned glyphs
+type Label<'a> = (String, Vec<(usize, PositionedGlyph<'a>)>);
+
+/// Glyphs of a single line of text paired with the index of their font, with the metrics of the line
+struct Line<'a> {
+    glyphs: Vec<(usize, PositionedGlyph<'a>)>,
+    width: f32,
+    ascent: f32,
+    descent: f32,
+}
+
+/// Find the first font of the chain with a glyph for the grapheme, falling back to the primary font.
+/// Whitespace continues the current run, so it doesn't split the text between fonts.
+fn pick_font(fonts: &[ChainFont], grapheme: &str, current: Option<usize>) -> usize {
+    let c = match grapheme.chars().next() {
+        Some(c) => c,
+        None => return 0,
+    };
+
+    if c.is_whitespace() {
+        if let Some(index) = current {
+            return index;
+        }
+    }
+
+    fonts.iter().position(|f| font::covers(&f.font, c)).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use badge::{BadgeKind, BadgePosition};
+    use font::test_font;
+
+    /// Math face with other vertical metrics and without the Hebrew letters of DejaVu Sans
+    const DEJAVU_MATH: &[u8] = include_bytes!("../tests/fonts/DejaVuMathTeXGyre.ttf");
+    const DEJAVU_SANS: &[u8] = include_bytes!("../tests/fonts/DejaVuSans.ttf");
+    /// Monospaced face without the Urdu letters of DejaVu Sans, e.g. ddal
+    #[cfg(feature = "shaping")]
+    const DEJAVU_MONO: &[u8] = include_bytes!("../tests/fonts/DejaVuSansMono.ttf");
 
-        for glyph in glyphs {
-            if let Some(bounding_box) = glyph.pixel_bounding_box() {
-                // draw the glyph into the image according to font color
-                glyph.draw(|x, y, v| {
-                    image.put_pixel(
-                        x + bounding_box.min.x as u32 + left_padding,
-                        y + bounding_box.min.y as u32 + top_padding,
-                        font_color.to_rgba((v * 255.0) as u8),
-                    )
-                });
+    fn builder(name: &str) -> AvatarBuilder {
+        AvatarBuilder::new(name).with_font_source(test_font()).unwrap()
+    }
+
+    fn fallback_chain(name: &str) -> AvatarBuilder {
+        AvatarBuilder::new(name)
+            .with_font_source(FontSource::Bytes(Arc::from(DEJAVU_MATH)))
+            .unwrap()
+            .with_font_fallbacks(vec![FontSource::Bytes(Arc::from(DEJAVU_SANS))])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_deterministic_colors() {
+        let avatar = || builder("Anakin Skywalker").with_deterministic_colors().unwrap();
+        assert_eq!(avatar().colors(&[]).unwrap(), avatar().colors(&[]).unwrap());
+        assert_eq!(avatar().draw().unwrap().into_raw(), avatar().draw().unwrap().into_raw());
+
+        // the full name is hashed, not only the initials
+        let other = builder("Amidala Skywalker").with_deterministic_colors().unwrap();
+        assert_ne!(avatar().colors(&[]).unwrap(), other.colors(&[]).unwrap());
+    }
+
+    #[test]
+    fn test_seeded_colors_honor_contrast_ratio() {
+        for seed in 0..32 {
+            let (font_color, background_color) = builder("Lucky Seven")
+                .with_seed(seed).unwrap()
+                .colors(&[])
+                .unwrap();
+            assert!(font_color.find_ratio(&background_color.average()) >= 4.5);
+        }
+    }
+
+    #[test]
+    fn test_apca_colors_honor_contrast() {
+        for seed in 0..32 {
+            let (font_color, background_color) = builder("Lucky Seven")
+                .with_seed(seed).unwrap()
+                .with_contrast_algorithm(ContrastAlgorithm::Apca).unwrap()
+                .with_contrast_ratio(60.).unwrap()
+                .colors(&[])
+                .unwrap();
+            assert!(font_color.apca_contrast(&background_color.average()).abs() >= 60.);
+        }
+    }
+
+    #[test]
+    fn test_unsatisfiable_contrast() {
+        let res = builder("Lucky Seven")
+            .with_font_color("#808080").unwrap()
+            .with_contrast_ratio(21.).unwrap()
+            .draw();
+        assert!(res.is_err());
+        assert_eq!(
+            format!("{}", res.unwrap_err()),
+            "unable to find colors satisfying the contrast ratio: 21"
+        );
+    }
+
+    #[test]
+    fn test_contrast_falls_back_to_lightness() {
+        // only black reaches the ratio against the white font
+        let (font_color, background_color) = builder("Lucky Seven")
+            .with_font_color("#FFFFFF").unwrap()
+            .with_contrast_ratio(20.9).unwrap()
+            .with_seed(7).unwrap()
+            .colors(&[])
+            .unwrap();
+        assert_eq!(font_color, RgbColor::new(255, 255, 255));
+        assert!(font_color.find_ratio(&background_color.average()) >= 20.9);
+    }
+
+    #[test]
+    fn test_gradient_background() {
+        let black_to_white = vec![(0., RgbColor::new(0, 0, 0)), (1., RgbColor::new(255, 255, 255))];
+        let image = builder("Lucky Seven")
+            .with_font_color("#FF0000").unwrap()
+            .with_background(Background::LinearGradient { angle: 90., stops: black_to_white }).unwrap()
+            .draw()
+            .unwrap();
+        assert_eq!(image.get_pixel(0, 0).data, [0, 0, 0, 255]);
+        assert_eq!(image.get_pixel(299, 0).data, [255, 255, 255, 255]);
+
+        let empty = Background::RadialGradient { center: (0.5, 0.5), stops: Vec::new() };
+        match builder("Lucky Seven").with_background(empty) {
+            Err(Error::InvalidGradient) => (),
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_random_gradient() {
+        let avatar = || {
+            builder("Anakin Skywalker")
+                .with_deterministic_colors().unwrap()
+                .with_random_gradient().unwrap()
+        };
+        let (font_color, background) = avatar().colors(&[]).unwrap();
+        assert_eq!((font_color, background.clone()), avatar().colors(&[]).unwrap());
+
+        // the contrast holds against both stops
+        match background {
+            Background::LinearGradient { ref stops, .. } => {
+                assert_eq!(stops.len(), 2);
+                assert!(stops.iter().all(|&(_, color)| font_color.find_ratio(&color) >= 4.5));
             }
+            background => panic!("unexpected background: {:?}", background),
         }
+    }
+
+    #[test]
+    fn test_gradient_contrast_under_glyphs() {
+        // the text in the middle is only over black, the white sides are far from it
+        let white = RgbColor::new(255, 255, 255);
+        let black = RgbColor::new(0, 0, 0);
+        let background = Background::LinearGradient {
+            angle: 90.,
+            stops: vec![(0., white), (0.1, white), (0.2, black), (0.8, black), (0.9, white), (1., white)],
+        };
+        let avatar = builder("Lucky Seven")
+            .with_width(600).unwrap()
+            .with_font_scale(60.).unwrap()
+            .with_background(background).unwrap()
+            .with_contrast_ratio(15.).unwrap()
+            .with_seed(1).unwrap();
+
+        let fonts = avatar.fonts().unwrap();
+        let glyphs = avatar.layout(&fonts).unwrap();
+        let (font_color, _) = avatar.colors(&glyphs).unwrap();
+        assert!(font_color.find_ratio(&black) >= 15.);
+
+        // no font color has the contrast against the whole background
+        match avatar.colors(&[]) {
+            Err(Error::UnsatisfiableContrast { .. }) => (),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_text_effects() {
+        let avatar = || {
+            builder("Lucky Seven")
+                .with_font_color("#FFFFFF").unwrap()
+                .with_background_color("#336699").unwrap()
+        };
+        let plain = avatar().draw().unwrap();
 
-        for (_, _, pixel) in image.enumerate_pixels_mut() {
-            // put background pixels for the uncovered alpha channels
-            if pixel.data[3] == 0 {
-                *pixel = background_color.to_rgba(255)
+        // the stroke surrounds the text without covering it
+        let stroked = avatar().with_text_stroke(3., "#FF0000").unwrap().draw().unwrap();
+        assert!(stroked.pixels().any(|p| p.data == [255, 0, 0, 255]));
+        for (p, s) in plain.pixels().zip(stroked.pixels()) {
+            if p.data == [255, 255, 255, 255] {
+                assert_eq!(s.data, p.data);
             }
         }
 
-        // apply gaussian blur to the image if specified
-        if let Some(b) = self.blur {
-            imageops::blur(&image, b)
-        } else {
-            image
+        // the shadow is soft and the text stays sharp
+        let shadowed = avatar().with_drop_shadow((4., 4.), 3., "#000000").unwrap().draw().unwrap();
+        assert_eq!(
+            shadowed.pixels().filter(|p| p.data == [255, 255, 255, 255]).count(),
+            plain.pixels().filter(|p| p.data == [255, 255, 255, 255]).count()
+        );
+        assert!(shadowed.pixels().any(|p| p.data[0] < 0x33 && p.data[0] > 0));
+
+        let glowing = avatar().with_outer_glow(8., "#FFFF00").unwrap().draw().unwrap();
+        assert!(glowing.pixels().zip(plain.pixels()).any(|(g, p)| g.data[2] < p.data[2]));
+
+        match avatar().with_text_stroke(-1., "#000000") {
+            Err(Error::InvalidEffectSize(size)) => assert_eq!(size, -1.),
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_effects_scale_with_the_avatar() {
+        let avatar = builder("Lucky Seven")
+            .with_font_color("#FFFFFF").unwrap()
+            .with_background_color("#000000").unwrap()
+            .with_text_stroke(2., "#FF0000").unwrap();
+        // the red of the stroke is the only difference between the red and the green channels
+        let area = |image: &ImageBuffer<Rgba<u8>, Vec<u8>>| {
+            image.pixels().map(|p| f32::from(p.data[0] - p.data[1]) / 255.).sum::<f32>()
+        };
+
+        // the area of the stroke grows with the square of the ratio
+        let images = avatar.draw_densities(&[1., 2.]).unwrap();
+        let ratio = area(&images[1]) / area(&images[0]);
+        assert!(ratio > 3. && ratio < 5., "{}", ratio);
+    }
+
+    #[test]
+    fn test_layer_stack() {
+        // custom layer painting the left column
+        #[derive(Debug)]
+        struct Column;
+
+        impl Layer for Column {
+            fn draw(&self, canvas: &mut Canvas, context: &LayerContext) {
+                for y in 0..context.height {
+                    canvas.paint(0, y, Rgba([255, 0, 0, 255]));
+                }
+            }
+        }
+
+        let avatar = || {
+            builder("Lucky Seven")
+                .with_font_color("#FFFFFF").unwrap()
+                .with_background_color("#808080").unwrap()
+        };
+        let plain = avatar().draw().unwrap();
+
+        let image = avatar().with_layer(Column, LayerPosition::AboveText, BlendMode::Multiply).unwrap().draw().unwrap();
+        assert_eq!(image.get_pixel(0, 0).data, [128, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 0).data, [128, 128, 128, 255]);
+
+        // the pattern darkens the background but not the text, only the pixels
+        // barely uncovered by the text may be rounded differently
+        let dots = Pattern::Dots { radius: 3., spacing: 10., color: RgbColor::new(0, 0, 0) };
+        let image = avatar().with_pattern(dots, BlendMode::Multiply).unwrap().draw().unwrap();
+        assert_eq!(image.get_pixel(5, 5).data, [0, 0, 0, 255]);
+        for (p, i) in plain.pixels().zip(image.pixels()) {
+            if p.data == [255, 255, 255, 255] {
+                assert!(i.data[0] >= 254, "{:?}", i);
+            }
+        }
+
+        // the overlay covers the text
+        let overlay = ImageBuffer::from_pixel(1, 1, Rgba([0, 0, 255, 255]));
+        let image = avatar().with_overlay(overlay, BlendMode::Screen).unwrap().draw().unwrap();
+        assert!(image.pixels().all(|p| p.data[2] == 255));
+    }
+
+    #[test]
+    fn test_border() {
+        let avatar = || {
+            builder("Lucky Seven")
+                .with_font_color("#FFFFFF").unwrap()
+                .with_background_color("#336699").unwrap()
+        };
+
+        let image = avatar().with_border(10., "#FF0000").unwrap().draw().unwrap();
+        assert_eq!(image.get_pixel(0, 0).data, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(9, 150).data, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(10, 150).data, [0x33, 0x66, 0x99, 255]);
+
+        // the border follows the circle with anti-aliased edges on both sides
+        let image = avatar().with_shape(Shape::Circle).unwrap().with_border(10., "#FF0000").unwrap().draw().unwrap();
+        assert_eq!(image.get_pixel(0, 0).data[3], 0);
+        assert_eq!(image.get_pixel(5, 150).data, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(150, 294).data, [255, 0, 0, 255]);
+        assert!(image.pixels().any(|p| p.data[0] > 0x33 && p.data[0] < 255 && p.data[3] == 255));
+        assert!(image.pixels().any(|p| p.data[0] == 255 && p.data[3] > 0 && p.data[3] < 255));
+
+        match avatar().with_border(0., "#FF0000") {
+            Err(Error::InvalidBorderWidth(width)) => assert_eq!(width, 0.),
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_ring() {
+        let image = builder("Lucky Seven")
+            .with_font_color("#FFFFFF").unwrap()
+            .with_background_color("#336699").unwrap()
+            .with_shape(Shape::Circle).unwrap()
+            .with_ring(6., 4., "#00FF00").unwrap()
+            .draw()
+            .unwrap();
+
+        // ring at the edge, transparent gap and the avatar inside
+        assert_eq!(image.get_pixel(3, 150).data, [0, 255, 0, 255]);
+        assert_eq!(image.get_pixel(7, 150).data[3], 0);
+        assert_eq!(image.get_pixel(11, 150).data, [0x33, 0x66, 0x99, 255]);
+
+        match builder("Lucky Seven").with_ring(2., -1., "#00FF00") {
+            Err(Error::InvalidBorderWidth(gap)) => assert_eq!(gap, -1.),
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_border_shrinks_auto_fit() {
+        let avatar = builder("Lucky Seven")
+            .with_shape(Shape::Circle).unwrap()
+            .with_font_size(FontSize::Auto { max_fill: 1. }).unwrap();
+        let fonts = avatar.fonts().unwrap();
+        let plain = avatar.scale(&fonts).x;
+
+        let bordered = avatar.clone().with_border(10., "#FFFFFF").unwrap().with_ring(5., 5., "#FFFFFF").unwrap();
+        let (width, height) = bordered.text_size(&fonts, bordered.scale(&fonts));
+        assert!(bordered.scale(&fonts).x < plain);
+
+        // the text touches the inside of the border
+        let fit = Shape::Circle.fit_box_inset(width, height, 300, 300, 20.);
+        assert!((1. ..1.01).contains(&fit));
+
+        // no room is left inside the border and the ring
+        let covered = avatar.clone().with_border(100., "#FFFFFF").unwrap().with_ring(40., 10., "#FFFFFF").unwrap();
+        match covered.draw() {
+            Err(Error::InvalidBorderWidth(inset)) => assert_eq!(inset, 150.),
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+        assert!(avatar.with_border(149., "#FFFFFF").unwrap().draw().is_ok());
+    }
+
+    #[test]
+    fn test_badge() {
+        let avatar = builder("Lucky Seven")
+            .with_font_color("#000000").unwrap()
+            .with_background_color("#336699").unwrap();
+        let count = |count| Badge { position: BadgePosition::BottomRight, kind: BadgeKind::Count(count), size: 60. };
+        let image = avatar.clone().with_badge(count(120)).unwrap().draw().unwrap();
+
+        // red pill in the corner with the white label, the transparent gap and the avatar around
+        assert_eq!(image.get_pixel(290, 270).data, [229, 57, 53, 255]);
+        assert!(image.enumerate_pixels().any(|(x, y, p)| x > 200 && y > 240 && p.data == [255, 255, 255, 255]));
+        assert_eq!(image.get_pixel(280, 235).data[3], 0);
+        assert_eq!(image.get_pixel(280, 225).data, [0x33, 0x66, 0x99, 255]);
+
+        // the zero count hides the badge
+        let hidden = avatar.clone().with_badge(count(0)).unwrap().draw().unwrap();
+        assert_eq!(hidden.into_raw(), avatar.clone().draw().unwrap().into_raw());
+
+        // the badge scales with the avatar
+        let images = avatar.clone().with_badge(count(120)).unwrap().draw_densities(&[2.]).unwrap();
+        assert_eq!(images[0].get_pixel(580, 540).data, [229, 57, 53, 255]);
+        assert_eq!(images[0].get_pixel(560, 470).data[3], 0);
+
+        match avatar.with_badge(Badge { size: 0., ..count(1) }) {
+            Err(Error::InvalidBadgeSize(size)) => assert_eq!(size, 0.),
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_svg_decorations() {
+        let avatar = builder("Lucky Seven")
+            .with_shape(Shape::Circle).unwrap()
+            .with_border(10., "#FF0000").unwrap()
+            .with_ring(6., 4., "#00FF00").unwrap()
+            .with_text_stroke(2., "#000000").unwrap()
+            .with_badge(Badge { position: BadgePosition::BottomRight, kind: BadgeKind::Count(7), size: 60. }).unwrap();
+        let svg = avatar.clone().draw_svg_outlined().unwrap();
+
+        assert!(svg.contains("stroke=\"#FF0000\" stroke-width=\"10\""));
+        assert!(svg.contains("stroke=\"#00FF00\" stroke-width=\"6\""));
+        assert!(svg.contains("<g filter=\"url(#effects)\">"));
+        assert!(svg.contains("<g mask=\"url(#badge)\">"));
+        assert!(svg.contains("fill=\"#E53935\""));
+
+        // the white mask keeps the avatar around the badge, whose label is white too
+        assert_eq!(svg.matches("fill=\"#FFFFFF\"").count(), 2);
+
+        let dots = Pattern::Dots { radius: 3., spacing: 10., color: RgbColor::new(0, 0, 0) };
+        match avatar.with_pattern(dots, BlendMode::Multiply).unwrap().draw_svg() {
+            Err(Error::UnsupportedSvgLayers) => (),
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_invalid_dimensions() {
+        let res = builder("Lucky Seven").with_width(0);
+        assert_eq!(format!("{}", res.unwrap_err()), "invalid avatar dimensions: 0x300");
+        let res = builder("Lucky Seven").with_height(0);
+        assert_eq!(format!("{}", res.unwrap_err()), "invalid avatar dimensions: 300x0");
+        let res = builder("Lucky Seven").with_font_scale(-1.);
+        assert_eq!(format!("{}", res.unwrap_err()), "invalid font scale: -1");
+    }
+
+    #[test]
+    fn test_invalid_font() {
+        match builder("Lucky Seven").with_font_bytes(vec![0; 16]) {
+            Err(Error::InvalidFont) => (),
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_missing_font() {
+        let mut avatar = builder("Lucky Seven");
+        avatar.font_data = None;
+        match avatar.draw() {
+            Err(Error::MissingFont) => (),
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_font_fallbacks() {
+        let avatar = || builder("Lucky Seven").with_deterministic_colors().unwrap();
+        let chained = avatar()
+            .with_font_fallbacks(vec![FontSource::Bytes(Arc::from(DEJAVU_MATH))])
+            .unwrap();
+
+        // characters covered by the primary font are not affected by the chain
+        assert_eq!(chained.draw().unwrap().into_raw(), avatar().draw().unwrap().into_raw());
+
+        match avatar().with_font_fallbacks(vec![FontSource::Bytes(Arc::from(vec![0; 16]))]) {
+            Err(Error::InvalidFont) => (),
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_fallback_runs() {
+        let avatar = fallback_chain("Lucky \u{05E9}\u{05DC}\u{05D5}\u{05DD}");
+        assert_eq!(avatar.text(), "L\u{05E9}");
+        let fonts = avatar.fonts().unwrap();
+        assert!(!font::covers(&fonts[0].font, '\u{05E9}'));
+
+        // the hebrew letter is taken from the fallback instead of drawing the .notdef box
+        let scale = Scale::uniform(100.);
+        let line = avatar.line(&fonts, &avatar.text(), scale);
+        let indices: Vec<_> = line.glyphs.iter().map(|&(index, _)| index).collect();
+        assert_eq!(indices, vec![0, 1]);
+        assert_eq!(line.glyphs[1].1.id(), fonts[1].font.glyph('\u{05E9}').id());
+
+        // the runs share the baseline and the line spans the metrics of both fonts
+        let (primary, fallback) = (fonts[0].font.v_metrics(scale), fonts[1].font.v_metrics(scale));
+        assert!(line.glyphs.iter().all(|(_, glyph)| glyph.position().y == 0.));
+        assert!(fallback.ascent > primary.ascent && primary.descent < fallback.descent);
+        assert_eq!((line.ascent, line.descent), (fallback.ascent, primary.descent));
+
+        let glyphs = avatar.layout(&fonts).unwrap();
+        assert_eq!(glyphs[0].1.position().y, glyphs[1].1.position().y);
+        assert!(avatar.draw().is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "shaping")]
+    fn test_fallback_rtl_runs() {
+        let avatar = AvatarBuilder::new("\u{0639}\u{0644}\u{06CC} \u{0688}\u{0627}\u{0631}")
+            .with_font_source(FontSource::Bytes(Arc::from(DEJAVU_MONO)))
+            .unwrap()
+            .with_font_fallbacks(vec![FontSource::Bytes(Arc::from(DEJAVU_SANS))])
+            .unwrap();
+        assert_eq!(avatar.text(), "\u{0639}\u{0688}");
+        let fonts = avatar.fonts().unwrap();
+        assert!(font::covers(&fonts[0].font, '\u{0639}') && !font::covers(&fonts[0].font, '\u{0688}'));
+
+        // ain comes first, so it's drawn rightmost even though ddal comes from the fallback
+        let line = avatar.line(&fonts, &avatar.text(), Scale::uniform(100.));
+        let indices: Vec<_> = line.glyphs.iter().map(|&(index, _)| index).collect();
+        assert_eq!(indices, vec![1, 0]);
+        assert_eq!(line.glyphs[0].1.id(), fonts[1].font.glyph('\u{0688}').id());
+        assert_eq!(line.glyphs[1].1.id(), fonts[0].font.glyph('\u{0639}').id());
+        assert!(line.glyphs[0].1.position().x < line.glyphs[1].1.position().x);
+    }
+
+    #[test]
+    fn test_pick_font() {
+        let avatar = fallback_chain("Lucky Seven");
+        let fonts = avatar.fonts().unwrap();
+
+        assert_eq!(pick_font(&fonts, "A", None), 0);
+        assert_eq!(pick_font(&fonts, "\u{05E9}", Some(0)), 1);
+        // whitespace stays in the current run
+        assert_eq!(pick_font(&fonts, " ", Some(1)), 1);
+        // characters missing from the whole chain fall back to the primary font
+        assert_eq!(pick_font(&fonts, "\u{10FFFD}", Some(1)), 0);
+    }
+
+    #[test]
+    fn test_encode() {
+        let avatar = || {
+            builder("Lucky Seven")
+                .with_background_color("#336699").unwrap()
+                .with_font_color("#FFFFFF").unwrap()
+                .with_shape(Shape::Circle).unwrap()
+        };
+
+        let png = avatar().encode_to_vec(OutputFormat::Png { compression: Default::default() }).unwrap();
+        let decoded = image::load_from_memory(&png).unwrap().to_rgba();
+        assert_eq!(decoded.into_raw(), avatar().draw().unwrap().into_raw());
+
+        // transparent corners of the circle are flattened onto the background
+        let jpeg = avatar().encode_to_vec(OutputFormat::Jpeg { quality: 100 }).unwrap();
+        let decoded = image::load_from_memory(&jpeg).unwrap().to_rgba();
+        let corner = decoded.get_pixel(0, 0).data;
+        for (&c, &expected) in corner.iter().zip(&[0x33, 0x66, 0x99, 255]) {
+            assert!((i32::from(c) - expected).abs() <= 2);
+        }
+    }
+
+    #[test]
+    fn test_encode_ico() {
+        let ico = builder("Lucky Seven")
+            .encode_to_vec(OutputFormat::Ico { sizes: vec![16, 32, 48] })
+            .unwrap();
+        assert_eq!(&ico[..6], &[0, 0, 1, 0, 3, 0]);
+        assert_eq!(&ico[6 + 16 * 2..6 + 16 * 2 + 2], &[48, 48]);
+
+        match builder("Lucky Seven").encode_to_vec(OutputFormat::Ico { sizes: vec![512] }) {
+            Err(Error::InvalidDimensions { width: 512, height: 512 }) => (),
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+        match builder("Lucky Seven").encode_to_vec(OutputFormat::Ico { sizes: vec![] }) {
+            Err(Error::NoIconSizes) => (),
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_device_pixel_ratio() {
+        let avatar = builder("Lucky Seven")
+            .with_width(200).unwrap()
+            .with_height(100).unwrap()
+            .with_font_scale(60.).unwrap()
+            .with_font_color("#FFFFFF").unwrap()
+            .with_background_color("#000000").unwrap();
+
+        // bounding box of the text pixels
+        let text_box = |image: &ImageBuffer<Rgba<u8>, Vec<u8>>| {
+            let text: Vec<_> = image.enumerate_pixels().filter(|&(_, _, p)| p.data[0] > 128).collect();
+            let xs = text.iter().map(|&(x, _, _)| x);
+            let ys = text.iter().map(|&(_, y, _)| y);
+            (xs.clone().min().unwrap(), ys.clone().min().unwrap(), xs.max().unwrap(), ys.max().unwrap())
+        };
+
+        let images = avatar.clone().draw_densities(&[1., 2., 3.]).unwrap();
+        let dimensions: Vec<_> = images.iter().map(|i| i.dimensions()).collect();
+        assert_eq!(dimensions, vec![(200, 100), (400, 200), (600, 300)]);
+
+        let (left, top, right, bottom) = text_box(&images[0]);
+        for (image, ratio) in images.iter().zip(1..) {
+            let (l, t, r, b) = text_box(image);
+            for &(scaled, base) in &[(l, left), (t, top), (r + 1, right + 1), (b + 1, bottom + 1)] {
+                assert!((scaled as i32 - (base * ratio) as i32).abs() <= ratio as i32, "{} {}", scaled, base);
+            }
+        }
+
+        let retina = avatar.clone().with_device_pixel_ratio(2.).unwrap().draw().unwrap();
+        assert_eq!(retina.into_raw(), images[1].clone().into_raw());
+        assert!(avatar.clone().draw_densities(&[]).unwrap().is_empty());
+
+        match avatar.with_device_pixel_ratio(0.) {
+            Err(Error::InvalidPixelRatio(r)) => assert_eq!(r, 0.),
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_no_translucent_pixels() {
+        let avatar = || {
+            builder("Lucky Seven")
+                .with_font_color("#FFFFFF").unwrap()
+                .with_background_color("#336699").unwrap()
+        };
+
+        // the anti-aliased edges of the text are blended onto the opaque background
+        let image = avatar().draw().unwrap();
+        assert!(image.pixels().all(|p| p.data[3] == 255));
+        assert!(image.pixels().any(|p| p.data[0] > 0x33 && p.data[0] < 0xFF));
+
+        // only the edge of the shape is translucent
+        let image = avatar().with_shape(Shape::Circle).unwrap().with_supersampling(4).unwrap().draw().unwrap();
+        // distance of the farthest pixel edge from the center
+        let far = |c: u32| (c as f32 - 150.).abs().max((c as f32 - 149.).abs());
+        for (x, y, pixel) in image.enumerate_pixels() {
+            if far(x).hypot(far(y)) < 150. {
+                assert_eq!(pixel.data[3], 255, "translucent pixel at {}x{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_linear_blending() {
+        let avatar = |linear| {
+            builder("Lucky Seven")
+                .with_font_color("#FFFFFF").unwrap()
+                .with_background_color("#000000").unwrap()
+                .with_linear_blending(linear).unwrap()
+                .draw()
+                .unwrap()
+        };
+
+        // partially covered pixels are brighter when blended in linear light
+        let (srgb, linear) = (avatar(false), avatar(true));
+        let sum = |image: &ImageBuffer<Rgba<u8>, Vec<u8>>| image.pixels().map(|p| u64::from(p.data[0])).sum::<u64>();
+        assert!(sum(&linear) > sum(&srgb));
+        assert!(srgb.pixels().zip(linear.pixels()).all(|(s, l)| l.data[0] >= s.data[0]));
+    }
+
+    #[test]
+    fn test_invalid_supersampling() {
+        for &samples in &[0, 17] {
+            match builder("Lucky Seven").with_supersampling(samples) {
+                Err(Error::InvalidSupersampling(s)) => assert_eq!(s, samples),
+                res => panic!("unexpected result: {:?}", res.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_text_overflow() {
+        let res = builder("Lucky Seven")
+            .with_width(50).unwrap()
+            .with_height(50).unwrap()
+            .draw();
+        match res {
+            Err(Error::TextOverflow { canvas_width, canvas_height, .. }) => {
+                assert_eq!((canvas_width, canvas_height), (50, 50));
+            }
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_auto_font_size_fits_the_avatar() {
+        for name in &["Lucky", "Lucky Seven", "W W W"] {
+            for shape in &[Shape::Square, Shape::Circle, Shape::Squircle] {
+                let avatar = builder(name)
+                    .with_length(3).unwrap()
+                    .with_shape(*shape).unwrap()
+                    .with_font_size(FontSize::Auto { max_fill: 1. }).unwrap();
+                let fonts = avatar.fonts().unwrap();
+                let (width, height) = avatar.text_size(&fonts, avatar.scale(&fonts));
+
+                // the text touches the border of the available space
+                let fit = shape.fit_box(width, height, 300, 300);
+                assert!((1. ..1.01).contains(&fit));
+                assert!(avatar.draw().is_ok());
+            }
+        }
+
+        let res = builder("Lucky Seven").with_font_size(FontSize::Auto { max_fill: 1.5 });
+        assert_eq!(format!("{}", res.unwrap_err()), "invalid fill ratio: 1.5");
+        for &fill in &[0., -0.5, f32::NAN] {
+            match builder("Lucky Seven").with_font_size(FontSize::Auto { max_fill: fill }) {
+                Err(Error::InvalidFill(_)) => (),
+                res => panic!("unexpected result: {:?}", res.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_initials_are_grapheme_clusters() {
+        let corpus = [
+            ("Anakin Skywalker", "AS"),
+            ("\u{1F469}\u{200D}\u{1F4BB} Dev", "\u{1F469}\u{200D}\u{1F4BB}D"),
+            ("\u{1F1F9}\u{1F1F7} Onur", "\u{1F1F9}\u{1F1F7}O"),
+            ("E\u{301}lodie Martin", "E\u{301}M"),
+            ("e\u{301}lodie a\u{308}ra", "E\u{301}A\u{308}"),
+            ("\u{1F44B}\u{1F3FD} Wave", "\u{1F44B}\u{1F3FD}W"),
+            ("\u{1100}\u{1161}\u{11A8} Kim", "\u{1100}\u{1161}\u{11A8}K"),
+            ("\u{1100}\u{1161}\u{11A8}\u{1100}\u{1175}\u{11B7}", "\u{1100}\u{1161}\u{11A8}\u{1100}\u{1175}\u{11B7}"),
+        ];
+
+        for &(name, initials) in corpus.iter() {
+            let avatar = builder(name);
+            assert_eq!(avatar.name, initials);
+            assert_eq!(avatar.text(), initials);
+        }
+
+        let avatar = builder("\u{1F469}\u{200D}\u{1F4BB} Dev").with_length(1).unwrap();
+        assert_eq!(avatar.text(), "\u{1F469}\u{200D}\u{1F4BB}");
+    }
+
+    #[test]
+    fn test_strategy() {
+        use strategy::{Email, SkipParticles};
+
+        let avatar = builder("Ludwig van Beethoven");
+        assert_eq!(avatar.name, "LVB");
+        let avatar = avatar.with_strategy(SkipParticles).unwrap();
+        assert_eq!(avatar.name, "LB");
+
+        let avatar = builder("jane.doe@example.com").with_strategy(Email).unwrap();
+        assert_eq!(avatar.name, "JD");
+    }
+
+    #[test]
+    fn test_script_defaults() {
+        assert_eq!(AvatarBuilder::new("王小明").name, "王");
+        assert_eq!(AvatarBuilder::new("김민준").name, "민준");
+        assert_eq!(AvatarBuilder::new("Anakin Skywalker").name, "AS");
+
+        let avatar = AvatarBuilder::new("김민준").with_script(Script::Han).unwrap();
+        assert_eq!(avatar.name, "김");
+    }
+}
@@ -0,0 +1,283 @@
+//! Background module describes the solid and gradient fills behind the initials.
+use color::RgbColor;
+use error::Error;
+
+/// Fill of the avatar behind the text. Gradient stops are given as `(offset, color)`
+/// with the offsets in range `[0, 1]`, the colors are interpolated in sRGB.
+/// Gradients without stops are black, the builder rejects them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Background {
+    /// Single color
+    Solid(RgbColor),
+    /// Gradient along the angle in degrees, `0` points to the top and `90` to the right like CSS.
+    /// The line spans the avatar so the first and the last stops reach the corners
+    LinearGradient { angle: f32, stops: Vec<(f32, RgbColor)> },
+    /// Circular gradient from the center given relative to the size of the avatar,
+    /// `(0.5, 0.5)` is the middle. The last stop reaches the farthest corner
+    RadialGradient { center: (f32, f32), stops: Vec<(f32, RgbColor)> },
+}
+
+impl Background {
+    /// Calculate the color of the pixel at `(x, y)`.
+    pub fn color_at(&self, x: u32, y: u32, width: u32, height: u32) -> RgbColor {
+        let t = self.offset(x as f32 + 0.5, y as f32 + 0.5, width, height);
+        self.color_at_offset(t)
+    }
+
+    /// Calculate the colors that may appear inside the rectangle from `(left, top)`
+    /// to `(right, bottom)`: the colors at its nearest and farthest points and the stops between.
+    pub fn colors_in(
+        &self,
+        (left, top, right, bottom): (f32, f32, f32, f32),
+        width: u32,
+        height: u32,
+    ) -> Vec<RgbColor> {
+        let (min, max) = match *self {
+            Background::Solid(color) => return vec![color],
+            Background::LinearGradient { .. } => {
+                let corners = [(left, top), (right, top), (left, bottom), (right, bottom)];
+                let offsets = corners.iter().map(|&(x, y)| self.offset(x, y, width, height));
+                offsets.fold((1f32, 0f32), |(min, max), t| (min.min(t), max.max(t)))
+            }
+            Background::RadialGradient { center, .. } => {
+                let (cx, cy) = (center.0 * width as f32, center.1 * height as f32);
+                // nearest point of the rectangle is the center itself if it's inside
+                let (nx, ny) = (cx.max(left).min(right), cy.max(top).min(bottom));
+                let fx = if cx - left > right - cx { left } else { right };
+                let fy = if cy - top > bottom - cy { top } else { bottom };
+                (self.offset(nx, ny, width, height), self.offset(fx, fy, width, height))
+            }
+        };
+
+        let mut colors = vec![self.color_at_offset(min), self.color_at_offset(max)];
+        colors.extend(self.stops().iter().filter(|&&(t, _)| t > min && t < max).map(|&(_, color)| color));
+        colors
+    }
+
+    /// Calculate the average color of the stops, e.g. to flatten the transparent pixels onto.
+    pub fn average(&self) -> RgbColor {
+        match *self {
+            Background::Solid(color) => color,
+            _ => {
+                let stops = self.stops();
+                let first = stops.first().map_or_else(empty, |stop| stop.1);
+                stops
+                    .iter()
+                    .enumerate()
+                    .fold(first, |average, (i, &(_, color))| average.mix(&color, 1. / (i + 1) as f32))
+            }
+        }
+    }
+
+    /// Mix every color of the background with the other color, see `RgbColor::mix`.
+    pub fn mix(&self, other: &RgbColor, amount: f32) -> Background {
+        let mix = |stops: &[(f32, RgbColor)]| stops.iter().map(|&(t, c)| (t, c.mix(other, amount))).collect();
+        match *self {
+            Background::Solid(color) => Background::Solid(color.mix(other, amount)),
+            Background::LinearGradient { angle, ref stops } => Background::LinearGradient { angle, stops: mix(stops) },
+            Background::RadialGradient { center, ref stops } => Background::RadialGradient { center, stops: mix(stops) },
+        }
+    }
+
+    /// Start and end points of the gradient line of the linear gradient in pixels.
+    pub fn line(angle: f32, width: u32, height: u32) -> ((f32, f32), (f32, f32)) {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (w, h) = (width as f32, height as f32);
+        let half = (w * sin.abs() + h * cos.abs()) / 2.;
+        let (dx, dy) = (sin * half, -cos * half);
+        ((w / 2. - dx, h / 2. - dy), (w / 2. + dx, h / 2. + dy))
+    }
+
+    /// Center and radius of the radial gradient in pixels.
+    pub fn circle(center: (f32, f32), width: u32, height: u32) -> ((f32, f32), f32) {
+        let (w, h) = (width as f32, height as f32);
+        let (cx, cy) = (center.0 * w, center.1 * h);
+        let radius = cx.max(w - cx).hypot(cy.max(h - cy));
+        ((cx, cy), radius)
+    }
+
+    /// Sort the stops by their offsets and check the background can be drawn.
+    pub(crate) fn validated(mut self) -> Result<Background, Error> {
+        let valid = match self {
+            Background::Solid(_) => true,
+            Background::LinearGradient { angle, ref mut stops } => angle.is_finite() && sort(stops),
+            Background::RadialGradient { center, ref mut stops } => {
+                center.0.is_finite() && center.1.is_finite() && sort(stops)
+            }
+        };
+
+        if valid {
+            Ok(self)
+        } else {
+            Err(Error::InvalidGradient)
+        }
+    }
+
+    /// Position of the point on the gradient in range `[0, 1]`.
+    fn offset(&self, x: f32, y: f32, width: u32, height: u32) -> f32 {
+        let t = match *self {
+            Background::Solid(_) => 0.,
+            Background::LinearGradient { angle, .. } => {
+                let ((x1, y1), (x2, y2)) = Background::line(angle, width, height);
+                let (dx, dy) = (x2 - x1, y2 - y1);
+                let length = dx * dx + dy * dy;
+                if length == 0. {
+                    0.
+                } else {
+                    ((x - x1) * dx + (y - y1) * dy) / length
+                }
+            }
+            Background::RadialGradient { center, .. } => {
+                let ((cx, cy), radius) = Background::circle(center, width, height);
+                if radius == 0. {
+                    0.
+                } else {
+                    (x - cx).hypot(y - cy) / radius
+                }
+            }
+        };
+        t.clamp(0., 1.)
+    }
+
+    fn color_at_offset(&self, t: f32) -> RgbColor {
+        let stops = match *self {
+            Background::Solid(color) => return color,
+            _ => self.stops(),
+        };
+
+        let first = match stops.first() {
+            Some(&first) => first,
+            None => return empty(),
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        for pair in stops.windows(2) {
+            let ((start, from), (end, to)) = (pair[0], pair[1]);
+            if t <= end {
+                let amount = if end > start { (t - start) / (end - start) } else { 1. };
+                return from.mix(&to, amount);
+            }
+        }
+        stops[stops.len() - 1].1
+    }
+
+    fn stops(&self) -> &[(f32, RgbColor)] {
+        match *self {
+            Background::Solid(_) => &[],
+            Background::LinearGradient { ref stops, .. } | Background::RadialGradient { ref stops, .. } => stops,
+        }
+    }
+}
+
+impl From<RgbColor> for Background {
+    fn from(color: RgbColor) -> Background {
+        Background::Solid(color)
+    }
+}
+
+/// Color of the gradients without stops.
+fn empty() -> RgbColor {
+    RgbColor::new(0, 0, 0)
+}
+
+/// Sort the stops by offset, returns `false` if there are none or an offset is out of range.
+fn sort(stops: &mut [(f32, RgbColor)]) -> bool {
+    if stops.is_empty() || stops.iter().any(|&(t, _)| !(0. ..=1.).contains(&t)) {
+        return false;
+    }
+
+    stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(::std::cmp::Ordering::Equal));
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stops() -> Vec<(f32, RgbColor)> {
+        vec![(0., RgbColor::new(0, 0, 0)), (1., RgbColor::new(255, 255, 255))]
+    }
+
+    #[test]
+    fn test_linear_gradient() {
+        // left to right
+        let gradient = Background::LinearGradient { angle: 90., stops: stops() };
+        assert_eq!(gradient.color_at(0, 50, 100, 100), RgbColor::new(1, 1, 1));
+        assert_eq!(gradient.color_at(99, 50, 100, 100), RgbColor::new(254, 254, 254));
+        assert_eq!(gradient.color_at(49, 0, 100, 100), gradient.color_at(49, 99, 100, 100));
+
+        // the diagonal gradient reaches the corners
+        let gradient = Background::LinearGradient { angle: 135., stops: stops() };
+        assert_eq!(gradient.offset(0., 0., 100, 100), 0.);
+        assert_eq!(gradient.offset(100., 100., 100, 100), 1.);
+    }
+
+    #[test]
+    fn test_radial_gradient() {
+        let gradient = Background::RadialGradient { center: (0.5, 0.5), stops: stops() };
+        assert_eq!(gradient.offset(50., 50., 100, 100), 0.);
+        assert_eq!(gradient.offset(0., 0., 100, 100), 1.);
+        assert_eq!(gradient.color_at(10, 50, 100, 100), gradient.color_at(50, 10, 100, 100));
+    }
+
+    #[test]
+    fn test_color_stops() {
+        let gradient = Background::LinearGradient {
+            angle: 90.,
+            stops: vec![(0.25, RgbColor::new(255, 0, 0)), (0.75, RgbColor::new(0, 0, 255))],
+        };
+        assert_eq!(gradient.color_at_offset(0.), RgbColor::new(255, 0, 0));
+        assert_eq!(gradient.color_at_offset(0.5), RgbColor::new(128, 0, 128));
+        assert_eq!(gradient.color_at_offset(1.), RgbColor::new(0, 0, 255));
+    }
+
+    #[test]
+    fn test_colors_in() {
+        let gradient = Background::LinearGradient {
+            angle: 90.,
+            stops: vec![(0., RgbColor::new(0, 0, 0)), (0.5, RgbColor::new(255, 0, 0)), (1., RgbColor::new(255, 255, 255))],
+        };
+        // the left half never reaches the white stop
+        let colors = gradient.colors_in((0., 0., 40., 100.), 100, 100);
+        assert_eq!(colors, vec![RgbColor::new(0, 0, 0), RgbColor::new(204, 0, 0)]);
+        // the stop in the middle is included
+        let colors = gradient.colors_in((40., 0., 60., 100.), 100, 100);
+        assert!(colors.contains(&RgbColor::new(255, 0, 0)));
+
+        let radial = Background::RadialGradient { center: (0.5, 0.5), stops: stops() };
+        assert_eq!(radial.colors_in((40., 40., 60., 60.), 100, 100)[0], RgbColor::new(0, 0, 0));
+    }
+
+    #[test]
+    fn test_validated() {
+        let gradient = Background::LinearGradient { angle: 0., stops: stops().into_iter().rev().collect() };
+        assert_eq!(gradient.validated().unwrap(), Background::LinearGradient { angle: 0., stops: stops() });
+
+        for invalid in [
+            Background::LinearGradient { angle: 0., stops: Vec::new() },
+            Background::LinearGradient { angle: 0., stops: vec![(2., RgbColor::new(0, 0, 0))] },
+            Background::RadialGradient { center: (f32::NAN, 0.), stops: stops() },
+        ] {
+            match invalid.validated() {
+                Err(Error::InvalidGradient) => (),
+                res => panic!("unexpected result: {:?}", res),
+            }
+        }
+    }
+
+    #[test]
+    fn test_average() {
+        let gradient = Background::RadialGradient { center: (0.5, 0.5), stops: stops() };
+        assert_eq!(gradient.average(), RgbColor::new(128, 128, 128));
+        assert_eq!(Background::Solid(RgbColor::new(1, 2, 3)).average(), RgbColor::new(1, 2, 3));
+    }
+
+    #[test]
+    fn test_empty_stops() {
+        let gradient = Background::LinearGradient { angle: 0., stops: Vec::new() };
+        assert_eq!(gradient.average(), RgbColor::new(0, 0, 0));
+        assert_eq!(gradient.color_at(0, 0, 10, 10), RgbColor::new(0, 0, 0));
+        assert_eq!(gradient.colors_in((0., 0., 10., 10.), 10, 10), vec![RgbColor::new(0, 0, 0); 2]);
+    }
+}
@@ -0,0 +1,244 @@
+//! Badge module draws the status dot or the notification count over a corner of the avatar.
+use color::RgbColor;
+use effects::Mask;
+use layer::{Canvas, Layer, LayerContext};
+use shape::Shape;
+
+/// Largest count shown as a number, the larger ones are shown as `99+`
+pub const MAX_COUNT: u32 = 99;
+
+/// Width of the transparent gap around the badge relative to its size
+const GAP: f32 = 0.15;
+
+/// Font scale of the text relative to the size of the badge
+const TEXT_SCALE: f32 = 0.7;
+
+/// Space around the text of the wide badges relative to their size
+const PADDING: f32 = 0.5;
+
+/// Corner of the avatar the badge is drawn over.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum BadgePosition {
+    /// Top left corner
+    TopLeft,
+    /// Top right corner
+    TopRight,
+    /// Bottom left corner
+    BottomLeft,
+    /// Bottom right corner, where the chat apps usually show the status
+    #[default]
+    BottomRight,
+}
+
+/// Content of the badge.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BadgeKind {
+    /// Dot of the color, e.g. the online status
+    Dot(RgbColor),
+    /// Number of the unread notifications, `0` hides the badge
+    Count(u32),
+    /// Short text, e.g. `NEW`
+    Text(String),
+}
+
+/// Badge drawn over the corner of the avatar with a transparent gap cut around it.
+/// The counts and the texts are drawn in white on red with the fonts of the avatar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Badge {
+    /// Corner of the avatar
+    pub position: BadgePosition,
+    /// Dot, count or text
+    pub kind: BadgeKind,
+    /// Height of the badge in pixels, the counts and the texts grow wider to fit
+    pub size: f32,
+}
+
+impl Badge {
+    /// Text drawn on the badge, `None` for the dots.
+    pub fn label(&self) -> Option<String> {
+        match self.kind {
+            BadgeKind::Dot(_) => None,
+            BadgeKind::Count(count) if count > MAX_COUNT => Some(format!("{}+", MAX_COUNT)),
+            BadgeKind::Count(count) => Some(count.to_string()),
+            BadgeKind::Text(ref text) => Some(text.clone()),
+        }
+    }
+
+    /// Check if there's anything to draw, the zero count hides the badge.
+    pub fn is_visible(&self) -> bool {
+        self.kind != BadgeKind::Count(0)
+    }
+
+    /// Font scale of the label in pixels.
+    pub(crate) fn font_scale(&self) -> f32 {
+        self.size * TEXT_SCALE
+    }
+
+    /// Scale the badge by the factor, its label and the gap around it follow the size.
+    pub(crate) fn scaled(&self, factor: f32) -> Badge {
+        Badge { size: self.size * factor, ..self.clone() }
+    }
+
+    /// Calculate the center and the size of the badge for the label of the width in pixels.
+    /// The center is put on the outline of the shape towards the corner and moved inwards
+    /// until the badge fits inside the avatar, so it sits on the corner of the squares.
+    pub(crate) fn bounds(
+        &self,
+        label_width: Option<f32>,
+        shape: Shape,
+        width: u32,
+        height: u32,
+    ) -> ((f32, f32), (f32, f32)) {
+        let (w, h) = (width as f32, height as f32);
+        let badge_height = self.size.min(w).min(h);
+        let badge_width = label_width
+            .map_or(badge_height, |lw| (lw + self.size * PADDING).max(badge_height))
+            .min(w);
+
+        let (dx, dy) = shape.diagonal_outline(width, height);
+        let (dx, dy) = match self.position {
+            BadgePosition::TopLeft => (-dx, -dy),
+            BadgePosition::TopRight => (dx, -dy),
+            BadgePosition::BottomLeft => (-dx, dy),
+            BadgePosition::BottomRight => (dx, dy),
+        };
+        let cx = (w / 2. + dx).max(badge_width / 2.).min(w - badge_width / 2.);
+        let cy = (h / 2. + dy).max(badge_height / 2.).min(h - badge_height / 2.);
+        ((cx, cy), (badge_width, badge_height))
+    }
+
+    /// Fill of the badge.
+    pub(crate) fn color(&self) -> RgbColor {
+        match self.kind {
+            BadgeKind::Dot(color) => color,
+            _ => RgbColor::new(229, 57, 53),
+        }
+    }
+}
+
+/// Pill of the badge with its label, laid out in pixels of the avatar.
+#[derive(Debug)]
+pub(crate) struct BadgeLayer {
+    pub(crate) center: (f32, f32),
+    pub(crate) size: (f32, f32),
+    pub(crate) color: RgbColor,
+    pub(crate) label: Option<Mask>,
+    pub(crate) samples: u32,
+}
+
+impl BadgeLayer {
+    /// Width of the transparent gap cut around the badge in pixels.
+    pub(crate) fn gap(&self) -> f32 {
+        self.size.1 * GAP
+    }
+
+    /// Signed distance from the point to the pill, negative inside it.
+    fn distance(&self, x: f32, y: f32) -> f32 {
+        let radius = self.size.1 / 2.;
+        let px = ((x - self.center.0).abs() - (self.size.0 / 2. - radius)).max(0.);
+        px.hypot(y - self.center.1) - radius
+    }
+
+    /// Calculate the supersampled coverage of the pixel by the pill grown by the pixels.
+    fn coverage(&self, x: u32, y: u32, grow: f32) -> f32 {
+        let samples = self.samples.max(1);
+        let step = 1. / samples as f32;
+
+        let mut coverage = 0.;
+        for i in 0..samples * samples {
+            let px = x as f32 + (i % samples) as f32 * step + step / 2.;
+            let py = y as f32 + (i / samples) as f32 * step + step / 2.;
+            let distance = (self.distance(px, py) - grow) * samples as f32;
+            coverage += (0.5 - distance).clamp(0., 1.);
+        }
+        coverage / (samples * samples) as f32
+    }
+}
+
+impl Layer for BadgeLayer {
+    fn draw(&self, canvas: &mut Canvas, _context: &LayerContext) {
+        // cut the gap out of the avatar before drawing the badge into it
+        canvas.clip(|x, y| 1. - self.coverage(x, y, self.gap()));
+
+        for y in 0..canvas.height() {
+            for x in 0..canvas.width() {
+                let coverage = self.coverage(x, y, 0.);
+                if coverage > 0. {
+                    canvas.paint(x, y, self.color.to_rgba((coverage * 255.).round() as u8));
+                }
+            }
+        }
+
+        if let Some(ref label) = self.label {
+            let white = RgbColor::new(255, 255, 255);
+            for (x, y, coverage) in label.pixels() {
+                canvas.paint(x, y, white.to_rgba((coverage.min(1.) * 255.).round() as u8));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use background::Background;
+    use layer::BlendMode;
+
+    fn badge(kind: BadgeKind) -> Badge {
+        Badge { position: BadgePosition::BottomRight, kind, size: 20. }
+    }
+
+    #[test]
+    fn test_label() {
+        assert_eq!(badge(BadgeKind::Count(7)).label(), Some(String::from("7")));
+        assert_eq!(badge(BadgeKind::Count(99)).label(), Some(String::from("99")));
+        assert_eq!(badge(BadgeKind::Count(100)).label(), Some(String::from("99+")));
+        assert_eq!(badge(BadgeKind::Dot(RgbColor::new(0, 255, 0))).label(), None);
+        assert!(!badge(BadgeKind::Count(0)).is_visible());
+    }
+
+    #[test]
+    fn test_bounds() {
+        // the badge sits in the corner of the square
+        let dot = badge(BadgeKind::Dot(RgbColor::new(0, 255, 0)));
+        assert_eq!(dot.bounds(None, Shape::Square, 100, 100), ((90., 90.), (20., 20.)));
+        let top_left = Badge { position: BadgePosition::TopLeft, ..dot.clone() };
+        assert_eq!(top_left.bounds(None, Shape::Square, 100, 100), ((10., 10.), (20., 20.)));
+
+        // and on the outline of the circle
+        let ((cx, cy), _) = dot.bounds(None, Shape::Circle, 100, 100);
+        assert!((cx - 85.36).abs() < 0.01 && cx == cy);
+
+        // the label widens the badge
+        let count = badge(BadgeKind::Count(100));
+        assert_eq!(count.bounds(Some(30.), Shape::Square, 100, 100), ((80., 90.), (40., 20.)));
+    }
+
+    #[test]
+    fn test_cut_out() {
+        let background = Background::Solid(RgbColor::new(0, 0, 0));
+        let context = LayerContext {
+            width: 40,
+            height: 40,
+            scale: 1.,
+            font_color: RgbColor::new(255, 255, 255),
+            background: &background,
+        };
+        let layer = BadgeLayer {
+            center: (20., 20.),
+            size: (20., 20.),
+            color: RgbColor::new(0, 255, 0),
+            label: None,
+            samples: 1,
+        };
+        let mut canvas = Canvas::new(40, 40, false);
+        canvas.draw(&background, BlendMode::Normal, &context);
+        canvas.draw(&layer, BlendMode::Normal, &context);
+
+        let image = canvas.image();
+        assert_eq!(image.get_pixel(20, 20).data, [0, 255, 0, 255]);
+        // the gap around the badge is transparent and the avatar is left beyond it
+        assert_eq!(image.get_pixel(31, 20).data[3], 0);
+        assert_eq!(image.get_pixel(36, 20).data, [0, 0, 0, 255]);
+    }
+}
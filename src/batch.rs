@@ -0,0 +1,140 @@
+//! Batch module renders many avatars in order, in parallel with the `parallel` feature.
+use image::{ImageBuffer, Rgba};
+use std::collections::VecDeque;
+use avatar::AvatarBuilder;
+use error::Error;
+use renderer::Renderer;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Number of avatars rendered at once when the batch is sequential
+#[cfg(not(feature = "parallel"))]
+const CHUNK_SIZE: usize = 1;
+
+/// Number of avatars rendered at once by each thread
+#[cfg(feature = "parallel")]
+const CHUNK_SIZE_PER_THREAD: usize = 8;
+
+/// Image of the avatar or the error that stopped it
+type Rendered = Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Error>;
+
+/// Iterator over the images of the avatars, in the order of the input.
+/// Avatars are rendered in chunks as the iterator advances, so the results are streamed
+/// and a failing avatar doesn't stop the others.
+pub struct Batch<'a, I> {
+    renderer: &'a Renderer,
+    avatars: I,
+    rendered: VecDeque<Rendered>,
+}
+
+impl<'a, I> Batch<'a, I>
+where
+    I: Iterator,
+    I::Item: Into<AvatarBuilder>,
+{
+    pub(crate) fn new(renderer: &'a Renderer, avatars: I) -> Batch<'a, I> {
+        Batch {
+            renderer,
+            avatars,
+            rendered: VecDeque::new(),
+        }
+    }
+
+    /// Render the next chunk of the avatars
+    #[cfg(not(feature = "parallel"))]
+    fn render(&mut self) {
+        let renderer = self.renderer;
+        self.rendered
+            .extend(self.avatars.by_ref().take(CHUNK_SIZE).map(|avatar| renderer.draw(&avatar.into())));
+    }
+
+    /// Render the next chunk of the avatars on the threads of the rayon pool,
+    /// the parsed fonts and the glyph cache of the renderer are shared between them
+    #[cfg(feature = "parallel")]
+    fn render(&mut self) {
+        let chunk: Vec<AvatarBuilder> = self
+            .avatars
+            .by_ref()
+            .take(rayon::current_num_threads() * CHUNK_SIZE_PER_THREAD)
+            .map(Into::into)
+            .collect();
+
+        let renderer = self.renderer;
+        let mut rendered = Vec::with_capacity(chunk.len());
+        chunk
+            .par_iter()
+            .map(|avatar| renderer.draw(avatar))
+            .collect_into_vec(&mut rendered);
+        self.rendered.extend(rendered);
+    }
+}
+
+impl<'a, I> Iterator for Batch<'a, I>
+where
+    I: Iterator,
+    I::Item: Into<AvatarBuilder>,
+{
+    type Item = Rendered;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rendered.is_empty() {
+            self.render();
+        }
+        self.rendered.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use font::test_font;
+
+    fn renderer() -> Renderer {
+        Renderer::with_fonts(test_font(), Vec::new()).unwrap()
+    }
+
+    #[test]
+    fn test_batch_preserves_order() {
+        let renderer = renderer();
+        let names = ["Lucky Seven", "Anakin Skywalker", "王小明", "Ludwig van Beethoven"];
+        let avatar = |name: &str| {
+            AvatarBuilder::new(name)
+                .with_font_source(test_font())
+                .and_then(AvatarBuilder::with_deterministic_colors)
+                .unwrap()
+        };
+
+        let images: Vec<_> = renderer
+            .draw_batch((0..25).map(|i| avatar(names[i % names.len()])))
+            .collect();
+        assert_eq!(images.len(), 25);
+        for (i, image) in images.into_iter().enumerate() {
+            let expected = avatar(names[i % names.len()]).draw().unwrap();
+            assert_eq!(image.unwrap().into_raw(), expected.into_raw());
+        }
+    }
+
+    #[test]
+    fn test_batch_of_names() {
+        let renderer = renderer();
+        assert_eq!(renderer.draw_batch(vec!["Lucky Seven", "王小明"]).filter(Result::is_ok).count(), 2);
+    }
+
+    #[test]
+    fn test_batch_errors_per_avatar() {
+        let renderer = renderer();
+        let avatars = vec![
+            AvatarBuilder::new("Lucky Seven"),
+            AvatarBuilder::new("Lucky Seven").with_width(10).unwrap(),
+            AvatarBuilder::new("Lucky Seven"),
+        ];
+
+        let results: Vec<_> = renderer.draw_batch(avatars).collect();
+        assert!(results[0].is_ok());
+        match results[1] {
+            Err(Error::TextOverflow { .. }) => (),
+            ref res => panic!("unexpected result: {:?}", res.as_ref().map(|_| ())),
+        }
+        assert!(results[2].is_ok());
+    }
+}
@@ -1,6 +1,5 @@
 //! Color module that helps generating and operating on rgb colors
 use std::str::FromStr;
-use std::u8;
 use std::iter::FromIterator;
 use image::Rgba;
 use error::Error;
@@ -22,9 +21,59 @@ impl RgbColor {
         RgbColor(rng.gen(), rng.gen(), rng.gen())
     }
 
-    /// Calculate the contrast ratio between colors
+    /// Generate a deterministic rgb color from the seed state and advance the state.
+    /// Uses SplitMix64, so the sequence is identical across platforms and crate versions.
+    pub fn seeded(state: &mut u64) -> Self {
+        *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        RgbColor((z >> 16) as u8, (z >> 8) as u8, z as u8)
+    }
+
+    /// Mix the color with the other color. `amount` of `0` keeps the color
+    /// and `1` results in the other color.
+    pub fn mix(&self, other: &RgbColor, amount: f32) -> RgbColor {
+        let mix = |a: u8, b: u8| (f32::from(a) + (f32::from(b) - f32::from(a)) * amount).round() as u8;
+        RgbColor(mix(self.0, other.0), mix(self.1, other.1), mix(self.2, other.2))
+    }
+
+    /// Calculate the WCAG 2.x contrast ratio between colors in range `[1, 21]`.
     pub fn find_ratio(&self, other: &RgbColor) -> f32 {
-        self.calculate_luminance() / other.calculate_luminance()
+        let (l1, l2) = (self.relative_luminance(), other.relative_luminance());
+        (l1.max(l2) + 0.05) / (l1.min(l2) + 0.05)
+    }
+
+    /// Calculate the APCA lightness contrast (`Lc`) of the color as text over the background.
+    /// Positive for dark text on light background and negative for the reverse polarity.
+    pub fn apca_contrast(&self, background: &RgbColor) -> f32 {
+        let (text, background) = (self.apca_luminance(), background.apca_luminance());
+        if (background - text).abs() < 0.0005 {
+            return 0.;
+        }
+
+        let sapc = if background > text {
+            (background.powf(0.56) - text.powf(0.57)) * 1.14
+        } else {
+            (background.powf(0.65) - text.powf(0.62)) * 1.14
+        };
+
+        let lc = if sapc.abs() < 0.1 {
+            0.
+        } else if sapc > 0. {
+            sapc - 0.027
+        } else {
+            sapc + 0.027
+        };
+
+        lc * 100.
+    }
+
+    /// Calculate the WCAG relative luminance from the linearized sRGB channels.
+    pub fn relative_luminance(&self) -> f32 {
+        0.2126 * linearize(self.0) + 0.7152 * linearize(self.1) + 0.0722 * linearize(self.2)
     }
 
     /// Convert to rgba (including transparency) for image creation
@@ -34,14 +83,95 @@ impl RgbColor {
         }
     }
 
-    fn calculate_luminance(&self) -> f32 {
-        0.299 * f32::from(self.0) +
-        0.587 * f32::from(self.1) +
-        0.114 * f32::from(self.2) +
-        0.05
+    /// Format the color as hex code. Example: `#00FF00`
+    pub fn to_hex(&self) -> String {
+        format!("#{:02X}{:02X}{:02X}", self.0, self.1, self.2)
+    }
+
+    /// Estimated screen luminance of the APCA-W3 model with the soft clamp for dark colors.
+    fn apca_luminance(&self) -> f32 {
+        let y = 0.212_672_9 * (f32::from(self.0) / 255.).powf(2.4)
+            + 0.715_152_2 * (f32::from(self.1) / 255.).powf(2.4)
+            + 0.072_175 * (f32::from(self.2) / 255.).powf(2.4);
+
+        if y < 0.022 {
+            y + (0.022 - y).powf(1.414)
+        } else {
+            y
+        }
+    }
+}
+
+/// Algorithm used to measure the contrast between the font and background colors.
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub enum ContrastAlgorithm {
+    /// WCAG 2.x contrast ratio in range `[1, 21]`. AA compliance requires `4.5`.
+    #[default]
+    Wcag,
+    /// APCA lightness contrast (`|Lc|`) in range `[0, 108]`. Body text requires `75`.
+    Apca,
+}
+
+impl ContrastAlgorithm {
+    /// Calculate the contrast of the font color over the background color.
+    pub fn contrast(&self, font: &RgbColor, background: &RgbColor) -> f32 {
+        match *self {
+            ContrastAlgorithm::Wcag => font.find_ratio(background),
+            ContrastAlgorithm::Apca => font.apca_contrast(background).abs(),
+        }
+    }
+}
+
+/// Convert the sRGB channel to linear light in range `[0, 1]`.
+pub fn linearize(channel: u8) -> f32 {
+    let c = f32::from(channel) / 255.;
+    if c <= 0.040_45 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
     }
 }
 
+/// Convert the linear light in range `[0, 1]` back to the sRGB channel.
+pub fn delinearize(linear: f32) -> u8 {
+    let c = linear.clamp(0., 1.);
+    let c = if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    };
+    (c * 255.).round() as u8
+}
+
+/// Composite the source pixel over the destination pixel (Porter-Duff source-over).
+/// With `linear` the colors are blended in linear light instead of sRGB,
+/// which keeps the anti-aliased edges of light text on dark backgrounds from looking thin.
+pub fn source_over(destination: Rgba<u8>, source: Rgba<u8>, linear: bool) -> Rgba<u8> {
+    let (sa, da) = (f32::from(source.data[3]) / 255., f32::from(destination.data[3]) / 255.);
+    let alpha = sa + da * (1. - sa);
+    if alpha <= 0. {
+        return Rgba { data: [0, 0, 0, 0] };
+    }
+
+    let mut data = [0, 0, 0, (alpha * 255.).round() as u8];
+    for (i, channel) in data.iter_mut().take(3).enumerate() {
+        let (s, d) = (source.data[i], destination.data[i]);
+        *channel = if linear {
+            delinearize((linearize(s) * sa + linearize(d) * da * (1. - sa)) / alpha)
+        } else {
+            ((f32::from(s) * sa + f32::from(d) * da * (1. - sa)) / alpha).round() as u8
+        };
+    }
+    Rgba { data }
+}
+
+/// Stable FNV-1a hash of the name to seed the deterministic colors.
+pub fn name_seed(name: &str) -> u64 {
+    name.bytes().fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
 /// Parse hex code and generate RGB vector accordingly.
 impl FromStr for RgbColor {
     type Err = Error;
@@ -127,6 +257,43 @@ mod tests {
         assert_eq!(res.unwrap(), RgbColor(0, 255, 0));
     }
 
+    #[test]
+    fn test_to_hex() {
+        let res: RgbColor = "#0aff00".parse().unwrap();
+        assert_eq!(res.to_hex(), "#0AFF00");
+    }
+
+    #[test]
+    fn test_linearize() {
+        for c in 0..=255 {
+            assert_eq!(delinearize(linearize(c)), c);
+        }
+    }
+
+    #[test]
+    fn test_source_over() {
+        let (white, black) = (Rgba { data: [255, 255, 255, 255] }, Rgba { data: [0, 0, 0, 255] });
+        let half = Rgba { data: [255, 255, 255, 128] };
+        assert_eq!(source_over(black, white, false), white);
+        assert_eq!(source_over(black, half, false), Rgba { data: [128, 128, 128, 255] });
+        // half of the light in linear space is brighter in sRGB
+        assert_eq!(source_over(black, half, true), Rgba { data: [188, 188, 188, 255] });
+
+        // translucent pixels keep their own color
+        let transparent = Rgba { data: [0, 0, 0, 0] };
+        assert_eq!(source_over(transparent, half, false), half);
+    }
+
+    #[test]
+    fn test_seeded_colors_are_stable() {
+        assert_eq!(name_seed(""), 0xCBF2_9CE4_8422_2325);
+        assert_eq!(name_seed("a"), 0xAF63_DC4C_8601_EC8C);
+
+        let mut state = 0;
+        assert_eq!(RgbColor::seeded(&mut state), RgbColor(0x1D, 0xCD, 0xAF));
+        assert_eq!(state, 0x9E37_79B9_7F4A_7C15);
+    }
+
     #[test]
     fn test_contrast_ratio() {
         let rgb_white = RgbColor(255, 255, 255);
@@ -135,4 +302,32 @@ mod tests {
         let rgb_blue =  RgbColor(0, 0, 255);
         assert_eq!(rgb_white.find_ratio(&rgb_blue).floor(), 8.);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_wcag_reference_pairs() {
+        let white = RgbColor(255, 255, 255);
+        let black = RgbColor(0, 0, 0);
+        assert!((white.find_ratio(&black) - 21.).abs() < 0.01);
+        assert!((black.find_ratio(&white) - 21.).abs() < 0.01);
+        assert_eq!(white.find_ratio(&white), 1.);
+        // #767676 is the lightest grey passing AA on white, #777777 fails it
+        assert!((RgbColor(0x76, 0x76, 0x76).find_ratio(&white) - 4.54).abs() < 0.01);
+        assert!((RgbColor(0x77, 0x77, 0x77).find_ratio(&white) - 4.48).abs() < 0.01);
+        assert!((RgbColor(255, 0, 0).find_ratio(&white) - 4.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_apca_reference_pairs() {
+        let white = RgbColor(255, 255, 255);
+        let black = RgbColor(0, 0, 0);
+        let grey = RgbColor(0x88, 0x88, 0x88);
+        let light_grey = RgbColor(0xAA, 0xAA, 0xAA);
+        assert!((black.apca_contrast(&white) - 106.04).abs() < 0.05);
+        assert!((white.apca_contrast(&black) + 107.88).abs() < 0.05);
+        assert!((grey.apca_contrast(&white) - 63.06).abs() < 0.05);
+        assert!((white.apca_contrast(&grey) + 68.54).abs() < 0.05);
+        assert!((black.apca_contrast(&light_grey) - 58.15).abs() < 0.05);
+        assert!((light_grey.apca_contrast(&black) + 56.24).abs() < 0.05);
+        assert_eq!(white.apca_contrast(&white), 0.);
+    }
+}
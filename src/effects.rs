@@ -0,0 +1,247 @@
+//! Effects module draws the stroke, the shadow and the glow of the text as separate layers
+//! from the coverage of the glyphs, so the text itself stays sharp.
+use color::RgbColor;
+
+/// Coverage of the text in range `[0, 1]` for each pixel of the avatar.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Mask {
+    width: u32,
+    height: u32,
+    alpha: Vec<f32>,
+}
+
+impl Mask {
+    /// Construct an empty mask.
+    pub(crate) fn new(width: u32, height: u32) -> Mask {
+        Mask { width, height, alpha: vec![0.; (width * height) as usize] }
+    }
+
+    /// Get the coverage of the pixel, zero outside of the mask.
+    pub(crate) fn get(&self, x: i32, y: i32) -> f32 {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return 0.;
+        }
+        self.alpha[(y as u32 * self.width + x as u32) as usize]
+    }
+
+    /// Add the coverage to the pixel like overlapping glyphs are composited.
+    pub(crate) fn add(&mut self, x: i32, y: i32, alpha: f32) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return;
+        }
+        let a = &mut self.alpha[(y as u32 * self.width + x as u32) as usize];
+        *a += alpha * (1. - *a);
+    }
+
+    /// Iterate over the pixels with their coverage.
+    pub(crate) fn pixels(&self) -> impl Iterator<Item = (u32, u32, f32)> + '_ {
+        let width = self.width;
+        self.alpha
+            .iter()
+            .enumerate()
+            .map(move |(i, &alpha)| (i as u32 % width, i as u32 / width, alpha))
+    }
+
+    /// Grow the covered area by the radius with anti-aliased edges.
+    fn dilated(&self, radius: f32) -> Mask {
+        let reach = radius.ceil() as i32 + 1;
+        let mut kernel = Vec::new();
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let weight = (radius + 1. - (dx as f32).hypot(dy as f32)).clamp(0., 1.);
+                if weight > 0. {
+                    kernel.push((dx, dy, weight));
+                }
+            }
+        }
+
+        // spread the covered pixels only, the text covers a small part of the avatar
+        let mut dilated = Mask::new(self.width, self.height);
+        for (x, y, alpha) in self.pixels().filter(|&(_, _, alpha)| alpha > 0.) {
+            for &(dx, dy, weight) in &kernel {
+                let (x, y) = (x as i32 + dx, y as i32 + dy);
+                if x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
+                    let a = &mut dilated.alpha[(y as u32 * self.width + x as u32) as usize];
+                    *a = a.max(alpha * weight);
+                }
+            }
+        }
+        dilated
+    }
+
+    /// Apply gaussian blur with the standard deviation `sigma`.
+    fn blurred(&self, sigma: f32) -> Mask {
+        if sigma <= 0. {
+            return self.clone();
+        }
+
+        let radius = (sigma * 3.).ceil() as i32;
+        let kernel: Vec<f32> = (-radius..=radius).map(|i| (-(i * i) as f32 / (2. * sigma * sigma)).exp()).collect();
+        let sum: f32 = kernel.iter().sum();
+
+        // the gaussian is separable, blur the rows and then the columns
+        let mut rows = Mask::new(self.width, self.height);
+        let mut blurred = Mask::new(self.width, self.height);
+        for (x, y, _) in self.pixels() {
+            rows.alpha[(y * self.width + x) as usize] = (-radius..=radius)
+                .zip(&kernel)
+                .map(|(i, k)| self.get(x as i32 + i, y as i32) * k)
+                .sum::<f32>() / sum;
+        }
+        for (x, y, _) in self.pixels() {
+            blurred.alpha[(y * self.width + x) as usize] = (-radius..=radius)
+                .zip(&kernel)
+                .map(|(i, k)| rows.get(x as i32, y as i32 + i) * k)
+                .sum::<f32>() / sum;
+        }
+        blurred
+    }
+
+    /// Move the mask by the offset, sampling between the pixels for the fractional offsets.
+    fn offset(&self, dx: f32, dy: f32) -> Mask {
+        let (ix, iy) = (dx.floor() as i32, dy.floor() as i32);
+        let (fx, fy) = (dx - dx.floor(), dy - dy.floor());
+
+        let mut moved = Mask::new(self.width, self.height);
+        for (x, y, _) in self.pixels() {
+            let (sx, sy) = (x as i32 - ix, y as i32 - iy);
+            let top = self.get(sx, sy) * (1. - fx) + self.get(sx - 1, sy) * fx;
+            let bottom = self.get(sx, sy - 1) * (1. - fx) + self.get(sx - 1, sy - 1) * fx;
+            moved.alpha[(y * self.width + x) as usize] = top * (1. - fy) + bottom * fy;
+        }
+        moved
+    }
+}
+
+/// Outline around the text
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Stroke {
+    pub(crate) width: f32,
+    pub(crate) color: RgbColor,
+}
+
+/// Blurred copy of the text behind it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Shadow {
+    pub(crate) offset: (f32, f32),
+    pub(crate) blur: f32,
+    pub(crate) color: RgbColor,
+}
+
+/// Soft light around the text
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Glow {
+    pub(crate) radius: f32,
+    pub(crate) color: RgbColor,
+}
+
+/// Effects of the text, all sizes are in pixels.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub(crate) struct Effects {
+    pub(crate) stroke: Option<Stroke>,
+    pub(crate) shadow: Option<Shadow>,
+    pub(crate) glow: Option<Glow>,
+}
+
+impl Effects {
+    /// Scale the stroke width, the shadow offset and blur, and the glow radius by the factor.
+    pub(crate) fn scaled(&self, factor: f32) -> Effects {
+        Effects {
+            stroke: self.stroke.map(|s| Stroke { width: s.width * factor, ..s }),
+            shadow: self.shadow.map(|s| Shadow {
+                offset: (s.offset.0 * factor, s.offset.1 * factor),
+                blur: s.blur * factor,
+                ..s
+            }),
+            glow: self.glow.map(|g| Glow { radius: g.radius * factor, ..g }),
+        }
+    }
+
+    /// Draw the layers of the effects from the coverage of the text, from the bottom to the top.
+    pub(crate) fn layers(&self, text: &Mask) -> Vec<(RgbColor, Mask)> {
+        let mut layers = Vec::new();
+        if let Some(glow) = self.glow {
+            // half of the radius is solid and the other half fades out
+            layers.push((glow.color, text.dilated(glow.radius / 2.).blurred(glow.radius / 4.)));
+        }
+        if let Some(shadow) = self.shadow {
+            layers.push((shadow.color, text.offset(shadow.offset.0, shadow.offset.1).blurred(shadow.blur)));
+        }
+        if let Some(stroke) = self.stroke {
+            // the text is drawn over the stroke, so only the outer part of it is visible
+            layers.push((stroke.color, text.dilated(stroke.width)));
+        }
+        layers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dot() -> Mask {
+        let mut mask = Mask::new(21, 21);
+        mask.add(10, 10, 1.);
+        mask
+    }
+
+    #[test]
+    fn test_add() {
+        let mut mask = Mask::new(2, 2);
+        mask.add(0, 0, 0.5);
+        mask.add(0, 0, 0.5);
+        mask.add(5, 5, 1.);
+        assert_eq!(mask.get(0, 0), 0.75);
+        assert_eq!(mask.get(-1, 0), 0.);
+    }
+
+    #[test]
+    fn test_dilated() {
+        let dilated = dot().dilated(3.);
+        assert_eq!(dilated.get(13, 10), 1.);
+        assert_eq!(dilated.get(10, 7), 1.);
+        assert_eq!(dilated.get(15, 10), 0.);
+        // diagonal edge is anti-aliased
+        let edge = dilated.get(12, 13);
+        assert!(edge > 0. && edge < 1.);
+    }
+
+    #[test]
+    fn test_blurred() {
+        let blurred = dot().blurred(2.);
+        let total: f32 = blurred.pixels().map(|(_, _, a)| a).sum();
+        assert!((total - 1.).abs() < 0.01);
+        assert!(blurred.get(10, 10) > blurred.get(12, 10));
+        assert_eq!(blurred.get(11, 10), blurred.get(9, 10));
+        assert_eq!(dot().blurred(0.), dot());
+    }
+
+    #[test]
+    fn test_offset() {
+        let moved = dot().offset(2., -3.);
+        assert_eq!(moved.get(12, 7), 1.);
+        assert_eq!(moved.get(10, 10), 0.);
+
+        let half = dot().offset(0.5, 0.);
+        assert_eq!((half.get(10, 10), half.get(11, 10)), (0.5, 0.5));
+    }
+
+    #[test]
+    fn test_layers() {
+        let effects = Effects {
+            stroke: Some(Stroke { width: 2., color: RgbColor::new(0, 0, 0) }),
+            shadow: Some(Shadow { offset: (1., 1.), blur: 1., color: RgbColor::new(0, 0, 0) }),
+            glow: Some(Glow { radius: 4., color: RgbColor::new(255, 255, 255) }),
+        };
+        let layers = effects.layers(&dot());
+        assert_eq!(layers.len(), 3);
+        // glow at the bottom and stroke at the top
+        assert_eq!(layers[0].0, RgbColor::new(255, 255, 255));
+        assert_eq!(layers[2].1, dot().dilated(2.));
+        assert!(Effects::default().layers(&dot()).is_empty());
+
+        let scaled = effects.scaled(2.);
+        assert_eq!(scaled.stroke.unwrap().width, 4.);
+        assert_eq!(scaled.shadow.unwrap().offset, (2., 2.));
+    }
+}
@@ -0,0 +1,183 @@
+//! Encode module writes the avatar images in the common file formats.
+use image::{self, ImageBuffer, Rgba};
+use image::jpeg::JPEGEncoder;
+use image_webp::{self, WebPEncoder};
+use png;
+use std::io::Write;
+use color::RgbColor;
+use error::Error;
+
+/// File format of the encoded avatar.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputFormat {
+    /// PNG with the alpha channel
+    Png { compression: PngCompression },
+    /// JPEG with the quality in range `[1, 100]`. JPEG has no alpha channel,
+    /// so the transparent pixels are blended onto the background color
+    Jpeg { quality: u8 },
+    /// Lossless WebP with the alpha channel
+    WebP,
+    /// ICO with a PNG image for each size in range `[1, 256]`.
+    /// The avatar is drawn again for each size instead of being resized
+    Ico { sizes: Vec<u32> },
+}
+
+/// Compression level of the PNG encoder, trading the speed for the file size.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum PngCompression {
+    /// Fast minimal compression
+    Fast,
+    /// Balanced speed and size
+    #[default]
+    Default,
+    /// Smallest files
+    Best,
+}
+
+/// Encode the image as PNG.
+pub fn png<W: Write>(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    compression: PngCompression,
+    writer: W,
+) -> Result<(), Error> {
+    let mut encoder = png::Encoder::new(writer, image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(match compression {
+        PngCompression::Fast => png::Compression::Fast,
+        PngCompression::Default => png::Compression::Default,
+        PngCompression::Best => png::Compression::Best,
+    });
+
+    let mut writer = encoder.write_header().map_err(|e| Error::Encode(e.to_string()))?;
+    writer.write_image_data(image).map_err(|e| Error::Encode(e.to_string()))?;
+    writer.finish().map_err(|e| Error::Encode(e.to_string()))
+}
+
+/// Encode the image as JPEG, blending the transparent pixels onto the background color.
+pub fn jpeg<W: Write>(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    quality: u8,
+    background: RgbColor,
+    mut writer: W,
+) -> Result<(), Error> {
+    if !(1..=100).contains(&quality) {
+        return Err(Error::InvalidQuality(quality));
+    }
+
+    let background = background.to_rgba(255);
+    let mut data = Vec::with_capacity((image.width() * image.height() * 3) as usize);
+    for pixel in image.pixels() {
+        let alpha = u32::from(pixel.data[3]);
+        for (&c, &bg) in pixel.data[..3].iter().zip(&background.data[..3]) {
+            data.push(((u32::from(c) * alpha + u32::from(bg) * (255 - alpha) + 127) / 255) as u8);
+        }
+    }
+
+    JPEGEncoder::new_with_quality(&mut writer, quality).encode(&data, image.width(), image.height(), image::RGB(8))?;
+    Ok(())
+}
+
+/// Encode the image as lossless WebP.
+pub fn webp<W: Write>(image: &ImageBuffer<Rgba<u8>, Vec<u8>>, writer: W) -> Result<(), Error> {
+    WebPEncoder::new(writer)
+        .encode(image, image.width(), image.height(), image_webp::ColorType::Rgba8)
+        .map_err(|e| Error::Encode(e.to_string()))
+}
+
+/// Encode the images as a single ICO file, each image is stored as PNG.
+pub fn ico<W: Write>(images: &[ImageBuffer<Rgba<u8>, Vec<u8>>], mut writer: W) -> Result<(), Error> {
+    if images.is_empty() {
+        return Err(Error::NoIconSizes);
+    }
+
+    let mut entries = Vec::new();
+    let mut data = Vec::new();
+
+    // the directory of the images is followed by the image data
+    let mut offset = 6 + 16 * images.len() as u32;
+    for image in images {
+        let (width, height) = image.dimensions();
+        if width == 0 || width > 256 || height == 0 || height > 256 {
+            return Err(Error::InvalidDimensions { width, height });
+        }
+
+        let mut encoded = Vec::new();
+        png(image, PngCompression::Default, &mut encoded)?;
+
+        // sizes of 256 pixels are stored as zero
+        entries.extend_from_slice(&[width as u8, height as u8, 0, 0]);
+        entries.extend_from_slice(&1u16.to_le_bytes());
+        entries.extend_from_slice(&32u16.to_le_bytes());
+        entries.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+        entries.extend_from_slice(&offset.to_le_bytes());
+
+        offset += encoded.len() as u32;
+        data.extend_from_slice(&encoded);
+    }
+
+    writer.write_all(&[0, 0, 1, 0])?;
+    writer.write_all(&(images.len() as u16).to_le_bytes())?;
+    writer.write_all(&entries)?;
+    writer.write_all(&data)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: u32, height: u32) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        ImageBuffer::from_fn(width, height, |x, _| if x == 0 { Rgba([0, 0, 0, 0]) } else { Rgba([255, 0, 0, 255]) })
+    }
+
+    #[test]
+    fn test_png() {
+        let mut fast = Vec::new();
+        png(&image(64, 64), PngCompression::Fast, &mut fast).unwrap();
+        assert!(fast.starts_with(b"\x89PNG"));
+
+        let mut best = Vec::new();
+        png(&image(64, 64), PngCompression::Best, &mut best).unwrap();
+        assert!(best.len() <= fast.len());
+    }
+
+    #[test]
+    fn test_jpeg() {
+        let mut data = Vec::new();
+        jpeg(&image(16, 16), 90, RgbColor::new(255, 255, 255), &mut data).unwrap();
+        assert!(data.starts_with(&[0xFF, 0xD8]));
+
+        match jpeg(&image(16, 16), 0, RgbColor::new(255, 255, 255), &mut data) {
+            Err(Error::InvalidQuality(0)) => (),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_webp() {
+        let mut data = Vec::new();
+        webp(&image(16, 16), &mut data).unwrap();
+        assert_eq!(&data[..4], b"RIFF");
+        assert_eq!(&data[8..16], b"WEBPVP8L");
+    }
+
+    #[test]
+    fn test_ico() {
+        let mut data = Vec::new();
+        ico(&[image(16, 16), image(256, 256)], &mut data).unwrap();
+        assert_eq!(&data[..6], &[0, 0, 1, 0, 2, 0]);
+        assert_eq!(&data[6..8], &[16, 16]);
+        assert_eq!(&data[22..24], &[0, 0]);
+
+        // the first image starts after the directory
+        let offset = u32::from_le_bytes([data[18], data[19], data[20], data[21]]) as usize;
+        assert_eq!(offset, 6 + 16 * 2);
+        assert!(data[offset..].starts_with(b"\x89PNG"));
+
+        match ico(&[image(300, 300)], &mut data) {
+            Err(Error::InvalidDimensions { width: 300, height: 300 }) => (),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
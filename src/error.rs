@@ -1,4 +1,5 @@
 //! Error module includes the custom error types.
+#![allow(non_local_definitions)]
 use std::num::ParseIntError;
 use std::io;
 
@@ -16,7 +17,79 @@ pub enum Error {
     Parse(ParseIntError),
     /// IO read/write error
     #[fail(display = "IO error: {}", _0)]
-    IO(io::Error)
+    IO(io::Error),
+    /// Font data couldn't be parsed
+    #[fail(display = "invalid font data")]
+    InvalidFont,
+    /// No font is bundled and none was given to the avatar
+    #[fail(display = "no font to draw with: enable a bundled font feature or set a font with `with_font`")]
+    MissingFont,
+    /// Face index is out of the range of the font collection
+    #[fail(display = "font index {} is out of range for {} faces", index, count)]
+    FontIndexOutOfRange {
+        index: u32,
+        count: u32,
+    },
+    /// No installed font matches the family name
+    #[fail(display = "font family is not installed: {}", family)]
+    FontNotFound {
+        family: String,
+    },
+    /// Width or height of the avatar is zero
+    #[fail(display = "invalid avatar dimensions: {}x{}", width, height)]
+    InvalidDimensions {
+        width: u32,
+        height: u32,
+    },
+    /// Font scale is not a positive number
+    #[fail(display = "invalid font scale: {}", _0)]
+    InvalidFontScale(f32),
+    /// Fill ratio of the auto-fit font size is not in range `(0, 1]`
+    #[fail(display = "invalid fill ratio: {}", _0)]
+    InvalidFill(f32),
+    /// Device pixel ratio is not a positive number
+    #[fail(display = "invalid device pixel ratio: {}", _0)]
+    InvalidPixelRatio(f32),
+    /// Supersampling is not in range `[1, 16]`
+    #[fail(display = "invalid supersampling: {}", _0)]
+    InvalidSupersampling(u32),
+    /// Gradient has no stops, an offset out of range `[0, 1]` or a non-finite position
+    #[fail(display = "invalid gradient: stops need offsets in range [0, 1]")]
+    InvalidGradient,
+    /// Size of a text effect is not a positive number
+    #[fail(display = "invalid effect size: {}", _0)]
+    InvalidEffectSize(f32),
+    /// Width of the border, the ring or the gap is not a positive number
+    #[fail(display = "invalid border width: {}", _0)]
+    InvalidBorderWidth(f32),
+    /// Size of the badge is not a positive number
+    #[fail(display = "invalid badge size: {}", _0)]
+    InvalidBadgeSize(f32),
+    /// Text doesn't fit into the avatar
+    #[fail(display = "text of size {}x{} overflows the avatar of size {}x{}", text_width, text_height, canvas_width, canvas_height)]
+    TextOverflow {
+        text_width: u32,
+        text_height: u32,
+        canvas_width: u32,
+        canvas_height: u32,
+    },
+    /// JPEG quality is not in range `[1, 100]`
+    #[fail(display = "invalid JPEG quality: {}", _0)]
+    InvalidQuality(u8),
+    /// ICO image was requested without any sizes
+    #[fail(display = "no sizes given for the ICO image")]
+    NoIconSizes,
+    /// Custom layers, patterns and overlays can only be rasterized
+    #[fail(display = "layers can't be drawn in SVG")]
+    UnsupportedSvgLayers,
+    /// Image couldn't be encoded
+    #[fail(display = "unable to encode the image: {}", _0)]
+    Encode(String),
+    /// No colors satisfying the contrast ratio could be found
+    #[fail(display = "unable to find colors satisfying the contrast ratio: {}", ratio)]
+    UnsatisfiableContrast {
+        ratio: f32,
+    },
 }
 
 impl From<ParseIntError> for Error {
@@ -0,0 +1,251 @@
+//! Font module describes how the initials are sized and which fonts are used.
+use rusttype::Font;
+use std::fs::File;
+use std::io::prelude::*;
+use std::sync::{Arc, OnceLock};
+use ttf_parser::{self, name_id};
+use error::Error;
+use system;
+
+/// Size of the initials text.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum FontSize {
+    /// Uniform scale of the font in pixels
+    Fixed(f32),
+    /// Pick the largest scale so the text occupies `max_fill` (in range `(0, 1]`)
+    /// of the space available inside the avatar shape
+    Auto { max_fill: f32 },
+}
+
+impl Default for FontSize {
+    fn default() -> Self {
+        FontSize::Fixed(150.)
+    }
+}
+
+/// Source to load the font data from.
+#[derive(Debug, Clone)]
+pub enum FontSource {
+    /// Path of a `.ttf`, `.otf`, `.ttc` or `.otc` file
+    Path(String),
+    /// Data of a font file, e.g. embedded into the binary
+    Bytes(Arc<[u8]>),
+    /// Face at the index of a `.ttc` or `.otc` font collection
+    Face(Box<FontSource>, u32),
+    /// Installed font looked up by the family name and the weight (`400` regular, `700` bold)
+    /// in the standard fontconfig directories
+    System { family: String, weight: u16 },
+}
+
+impl FontSource {
+    /// Pick the face at the index of the font collection.
+    pub fn face(self, index: u32) -> FontSource {
+        FontSource::Face(Box::new(self), index)
+    }
+
+    /// Read the font data and make sure it can be used for drawing.
+    pub fn load(self) -> Result<FontData, Error> {
+        let (data, index) = self.read()?;
+
+        // single fonts are a collection of one face
+        let count = ttf_parser::fonts_in_collection(&data).unwrap_or(1);
+        if index >= count {
+            return Err(Error::FontIndexOutOfRange { index, count });
+        }
+
+        let font = FontData { data, index };
+        font.font()?;
+        Ok(font)
+    }
+
+    /// Read the data of the font file with the index of the face
+    fn read(self) -> Result<(Arc<[u8]>, u32), Error> {
+        match self {
+            FontSource::Path(path) => {
+                let mut data = Vec::new();
+                File::open(path)?.read_to_end(&mut data)?;
+                Ok((data.into(), 0))
+            }
+            FontSource::Bytes(data) => Ok((data, 0)),
+            FontSource::Face(source, index) => Ok((source.read()?.0, index)),
+            FontSource::System { family, weight } => system::find(&system::font_dirs(), &family, weight),
+        }
+    }
+}
+
+/// Data of a font file with the index of the face drawn from it.
+#[derive(Debug, Clone)]
+pub struct FontData {
+    data: Arc<[u8]>,
+    index: u32,
+}
+
+impl FontData {
+    /// Parse the face for drawing.
+    pub fn font(&self) -> Result<Font<'_>, Error> {
+        Font::try_from_bytes_and_index(&self.data, self.index).ok_or(Error::InvalidFont)
+    }
+
+    /// Raw data of the font file.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Index of the face in the font collection.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Read the family name of the face from its naming table.
+    pub fn family(&self) -> Option<String> {
+        ttf_parser::Face::parse(&self.data, self.index)
+            .ok()
+            .and_then(|face| family_name(face.names()))
+    }
+}
+
+impl FontData {
+    /// Font embedded by the `font-*` cargo features, `None` if no font is bundled.
+    /// The data is shared by all the avatars instead of being copied for each one.
+    pub fn bundled() -> Option<FontData> {
+        static BUNDLED: OnceLock<Option<FontData>> = OnceLock::new();
+        BUNDLED
+            .get_or_init(|| {
+                bundled_data().map(|data| FontData {
+                    data: Arc::from(data),
+                    index: 0,
+                })
+            })
+            .clone()
+    }
+}
+
+/// Parsed font of the fallback chain, the data is kept for the shaper.
+pub(crate) struct ChainFont<'a> {
+    pub(crate) font: Font<'a>,
+    pub(crate) data: FontData,
+}
+
+impl<'a> ChainFont<'a> {
+    /// Parse the font borrowing the data.
+    pub(crate) fn new(data: &'a FontData) -> Result<ChainFont<'a>, Error> {
+        Ok(ChainFont {
+            font: data.font()?,
+            data: data.clone(),
+        })
+    }
+}
+
+impl ChainFont<'static> {
+    /// Parse the font owning a copy of the data, so it can outlive the `FontData`.
+    pub(crate) fn owned(data: FontData) -> Result<ChainFont<'static>, Error> {
+        let font = Font::try_from_vec_and_index(data.data().to_vec(), data.index()).ok_or(Error::InvalidFont)?;
+        Ok(ChainFont { font, data })
+    }
+}
+
+#[cfg(feature = "font-hiragino")]
+fn bundled_data() -> Option<&'static [u8]> {
+    Some(include_bytes!("fonts/Hiragino_Sans_GB_W3.ttf"))
+}
+
+#[cfg(all(feature = "font-dejavu", not(feature = "font-hiragino")))]
+fn bundled_data() -> Option<&'static [u8]> {
+    Some(include_bytes!("fonts/DejaVuSans.ttf"))
+}
+
+#[cfg(not(any(feature = "font-hiragino", feature = "font-dejavu")))]
+fn bundled_data() -> Option<&'static [u8]> {
+    None
+}
+
+/// Font the tests draw with, so they pass without a bundled font.
+#[cfg(test)]
+pub(crate) fn test_font() -> FontSource {
+    static FONT: OnceLock<Arc<[u8]>> = OnceLock::new();
+    FontSource::Bytes(FONT.get_or_init(|| Arc::from(&include_bytes!("fonts/Hiragino_Sans_GB_W3.ttf")[..])).clone())
+}
+
+/// Read the family name of the face, preferring the typographic family that groups all weights.
+pub fn family_name(names: ttf_parser::name::Names) -> Option<String> {
+    [name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY]
+        .iter()
+        .filter_map(|&id| {
+            names
+                .into_iter()
+                .filter(|name| name.name_id == id)
+                .find_map(|name| name.to_string())
+        })
+        .find(|name| !name.is_empty())
+}
+
+/// Check if the font has a glyph for the character instead of the `.notdef` box.
+pub fn covers(font: &Font, c: char) -> bool {
+    font.glyph(c).id().0 != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> Arc<[u8]> {
+        Arc::from(&include_bytes!("fonts/Hiragino_Sans_GB_W3.ttf")[..])
+    }
+
+    #[test]
+    fn test_load() {
+        let font = FontSource::Bytes(data()).load().unwrap();
+        assert_eq!(font.index(), 0);
+        assert!(font.family().is_some());
+
+        match FontSource::Bytes(Arc::from(vec![0; 16])).load() {
+            Err(Error::InvalidFont) => {}
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+        match FontSource::Path(String::from("missing.ttf")).load() {
+            Err(Error::IO(_)) => {}
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_load_face() {
+        assert_eq!(FontSource::Bytes(data()).face(0).load().unwrap().index(), 0);
+        match FontSource::Bytes(data()).face(1).load() {
+            Err(Error::FontIndexOutOfRange { index: 1, count: 1 }) => {}
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_load_collection() {
+        // collection header with the same face listed twice
+        let font = data();
+        let offset = 20u32;
+        let mut collection = b"ttcf\x00\x01\x00\x00\x00\x00\x00\x02".to_vec();
+        collection.extend_from_slice(&offset.to_be_bytes());
+        collection.extend_from_slice(&offset.to_be_bytes());
+        collection.extend_from_slice(&font);
+
+        // table offsets of collections are relative to the start of the file
+        let tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+        for i in 0..tables {
+            let at = offset as usize + 12 + 16 * i + 8;
+            let table = u32::from_be_bytes([collection[at], collection[at + 1], collection[at + 2], collection[at + 3]]);
+            collection[at..at + 4].copy_from_slice(&(table + offset).to_be_bytes());
+        }
+
+        let face = FontSource::Bytes(Arc::from(collection)).face(1).load().unwrap();
+        assert_eq!(face.index(), 1);
+        assert_eq!(face.family(), FontData { data: font, index: 0 }.family());
+    }
+
+    #[test]
+    fn test_covers() {
+        let font = FontSource::Bytes(data()).load().unwrap();
+        let font = font.font().unwrap();
+        assert!(covers(&font, 'A'));
+        // private use area is not covered by the embedded font
+        assert!(!covers(&font, '\u{10FFFD}'));
+    }
+}
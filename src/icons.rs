@@ -0,0 +1,134 @@
+//! Icons module bundles the avatar as the favicon and the app icons of a website.
+use avatar::AvatarBuilder;
+use encode::{self, PngCompression};
+use error::Error;
+
+/// Sizes of the images inside the favicon
+const FAVICON_SIZES: [u32; 3] = [16, 32, 48];
+
+/// Size of the Apple touch icon
+const APPLE_TOUCH_ICON_SIZE: u32 = 180;
+
+/// Sizes of the Android Chrome icons
+const ANDROID_CHROME_SIZES: [u32; 2] = [192, 512];
+
+/// Favicon and app icons of the avatar. The avatar is drawn again at each size,
+/// so the text stays crisp in the small icons.
+#[derive(Debug, Clone)]
+pub struct IconSet {
+    /// `favicon.ico` with the 16, 32 and 48 pixel images
+    pub favicon: Vec<u8>,
+    /// `apple-touch-icon.png` of 180 pixels
+    pub apple_touch_icon: Vec<u8>,
+    /// `android-chrome-192x192.png`
+    pub android_chrome_192: Vec<u8>,
+    /// `android-chrome-512x512.png`
+    pub android_chrome_512: Vec<u8>,
+    /// `site.webmanifest` snippet listing the Android Chrome icons
+    pub manifest: String,
+}
+
+impl IconSet {
+    pub(crate) fn new(avatar: &AvatarBuilder) -> Result<IconSet, Error> {
+        let sizes: Vec<_> = FAVICON_SIZES
+            .iter()
+            .chain(&[APPLE_TOUCH_ICON_SIZE])
+            .chain(&ANDROID_CHROME_SIZES)
+            .map(|&size| (size, size))
+            .collect();
+        let (images, (_, background)) = avatar.draw_sizes(&sizes)?;
+
+        let mut favicon = Vec::new();
+        encode::ico(&images[..FAVICON_SIZES.len()], &mut favicon)?;
+
+        let mut pngs = Vec::new();
+        for image in &images[FAVICON_SIZES.len()..] {
+            let mut png = Vec::new();
+            encode::png(image, PngCompression::Best, &mut png)?;
+            pngs.push(png);
+        }
+
+        let icons: Vec<_> = ANDROID_CHROME_SIZES
+            .iter()
+            .map(|size| {
+                format!(
+                    "    {{ \"src\": \"/android-chrome-{s}x{s}.png\", \"sizes\": \"{s}x{s}\", \"type\": \"image/png\" }}",
+                    s = size
+                )
+            })
+            .collect();
+        let manifest = format!(
+            "{{\n  \"icons\": [\n{}\n  ],\n  \"theme_color\": \"{color}\",\n  \"background_color\": \"{color}\"\n}}\n",
+            icons.join(",\n"),
+            color = background.average().to_hex()
+        );
+
+        let android_chrome_512 = pngs.pop().unwrap_or_default();
+        let android_chrome_192 = pngs.pop().unwrap_or_default();
+        let apple_touch_icon = pngs.pop().unwrap_or_default();
+        Ok(IconSet {
+            favicon,
+            apple_touch_icon,
+            android_chrome_192,
+            android_chrome_512,
+            manifest,
+        })
+    }
+
+    /// List the icons with their conventional file names, ready to be written to the web root.
+    pub fn files(&self) -> Vec<(&'static str, &[u8])> {
+        vec![
+            ("favicon.ico", &self.favicon),
+            ("apple-touch-icon.png", &self.apple_touch_icon),
+            ("android-chrome-192x192.png", &self.android_chrome_192),
+            ("android-chrome-512x512.png", &self.android_chrome_512),
+            ("site.webmanifest", self.manifest.as_bytes()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use font::test_font;
+    use image;
+
+    #[test]
+    fn test_icon_set() {
+        let icons = AvatarBuilder::new("Lucky Seven")
+            .with_font_source(test_font()).unwrap()
+            .with_background_color("#336699").unwrap()
+            .draw_icons()
+            .unwrap();
+
+        assert_eq!(&icons.favicon[..6], &[0, 0, 1, 0, 3, 0]);
+        for &(png, size) in &[
+            (&icons.apple_touch_icon, 180),
+            (&icons.android_chrome_192, 192),
+            (&icons.android_chrome_512, 512),
+        ] {
+            let image = image::load_from_memory(png).unwrap().to_rgba();
+            assert_eq!(image.dimensions(), (size, size));
+        }
+
+        assert!(icons.manifest.contains("\"src\": \"/android-chrome-512x512.png\", \"sizes\": \"512x512\""));
+        assert!(icons.manifest.contains("\"theme_color\": \"#336699\""));
+        assert_eq!(icons.files().len(), 5);
+    }
+
+    #[test]
+    fn test_small_icons_are_drawn_again() {
+        let icons = AvatarBuilder::new("Lucky Seven")
+            .with_font_source(test_font()).unwrap()
+            .with_background_color("#000000").unwrap()
+            .with_font_color("#FFFFFF").unwrap()
+            .draw_icons()
+            .unwrap();
+
+        // the 16 pixel image still has the text in it
+        let offset = u32::from_le_bytes([icons.favicon[18], icons.favicon[19], icons.favicon[20], icons.favicon[21]]);
+        let image = image::load_from_memory(&icons.favicon[offset as usize..]).unwrap().to_rgba();
+        assert_eq!(image.dimensions(), (16, 16));
+        assert!(image.pixels().any(|p| p.data[0] > 128));
+    }
+}
@@ -0,0 +1,461 @@
+//! Layer module composites the avatar from a stack of layers drawn onto a shared canvas.
+//!
+//! From the bottom to the top the stack is the background, the layers below the text
+//! such as patterns, the border, the text with its effects and the layers above the text
+//! such as overlay images. The composited canvas is cut to the shape, the ring is drawn
+//! around it, the badge is drawn over the corner and the blur of the avatar is applied last.
+use image::{ImageBuffer, Rgba};
+use std::fmt::Debug;
+use std::sync::Arc;
+use background::Background;
+use color::{self, RgbColor};
+use effects::{Effects, Mask};
+use shape::Shape;
+
+/// Layer of the avatar. Implement it to draw custom designs with `AvatarBuilder::with_layer`.
+pub trait Layer: Debug + Send + Sync {
+    /// Draw the layer onto the canvas, the canvas blends the painted pixels with the blend mode of the layer.
+    fn draw(&self, canvas: &mut Canvas, context: &LayerContext);
+}
+
+/// How the colors of a layer are combined with the colors under it.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum BlendMode {
+    /// Layer color covers the colors under it
+    #[default]
+    Normal,
+    /// Product of the colors, always darker
+    Multiply,
+    /// Inverse product of the inverted colors, always lighter
+    Screen,
+    /// Multiply for the dark and screen for the light colors under the layer
+    Overlay,
+}
+
+impl BlendMode {
+    /// Blend the channels in range `[0, 1]` of the layer over the channel under it.
+    pub fn blend(&self, source: f32, destination: f32) -> f32 {
+        match *self {
+            BlendMode::Normal => source,
+            BlendMode::Multiply => source * destination,
+            BlendMode::Screen => source + destination - source * destination,
+            BlendMode::Overlay => {
+                if destination <= 0.5 {
+                    2. * source * destination
+                } else {
+                    1. - 2. * (1. - source) * (1. - destination)
+                }
+            }
+        }
+    }
+}
+
+/// Where a layer is placed in the stack.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LayerPosition {
+    /// Over the background and under the text, e.g. patterns
+    BelowText,
+    /// Over the text, e.g. overlay images
+    AboveText,
+}
+
+/// Metrics of the avatar given to the layers.
+#[derive(Debug)]
+pub struct LayerContext<'a> {
+    /// Width of the avatar in pixels
+    pub width: u32,
+    /// Height of the avatar in pixels
+    pub height: u32,
+    /// Factor of the pixel sizes, e.g. `2.0` for the avatar drawn at the device pixel ratio of `2.0`
+    pub scale: f32,
+    /// Resolved color of the text
+    pub font_color: RgbColor,
+    /// Resolved background
+    pub background: &'a Background,
+}
+
+/// RGBA image the layers are drawn onto.
+pub struct Canvas {
+    image: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    mode: BlendMode,
+    linear: bool,
+}
+
+impl Canvas {
+    /// Construct a transparent canvas. With `linear` the painted pixels are composited in linear light.
+    pub(crate) fn new(width: u32, height: u32, linear: bool) -> Canvas {
+        Canvas { image: ImageBuffer::new(width, height), mode: BlendMode::Normal, linear }
+    }
+
+    /// Width of the canvas.
+    pub fn width(&self) -> u32 {
+        self.image.width()
+    }
+
+    /// Height of the canvas.
+    pub fn height(&self) -> u32 {
+        self.image.height()
+    }
+
+    /// Composited image under the layer being drawn.
+    pub fn image(&self) -> &ImageBuffer<Rgba<u8>, Vec<u8>> {
+        &self.image
+    }
+
+    /// Paint the color over the pixel with the blend mode of the layer,
+    /// the alpha of the color is the coverage of the pixel.
+    pub fn paint(&mut self, x: u32, y: u32, color: Rgba<u8>) {
+        if color.data[3] == 0 || x >= self.image.width() || y >= self.image.height() {
+            return;
+        }
+
+        let pixel = self.image.get_pixel_mut(x, y);
+        if self.mode == BlendMode::Normal && color.data[3] == 255 {
+            *pixel = color;
+            return;
+        }
+
+        // the blended color replaces the color of the layer where there's something under it
+        let mut source = color;
+        let under = f32::from(pixel.data[3]) / 255.;
+        for i in 0..3 {
+            let (s, d) = (f32::from(color.data[i]) / 255., f32::from(pixel.data[i]) / 255.);
+            let blended = s * (1. - under) + self.mode.blend(s, d) * under;
+            source.data[i] = (blended * 255.).round() as u8;
+        }
+        *pixel = color::source_over(*pixel, source, self.linear);
+    }
+
+    /// Multiply the alpha of the pixels by the coverage, e.g. to cut the canvas to the shape.
+    pub(crate) fn clip<F: Fn(u32, u32) -> f32>(&mut self, coverage: F) {
+        for (x, y, pixel) in self.image.enumerate_pixels_mut() {
+            pixel.data[3] = (f32::from(pixel.data[3]) * coverage(x, y)).round() as u8;
+        }
+    }
+
+    /// Draw the layer onto the canvas with the blend mode.
+    pub(crate) fn draw(&mut self, layer: &dyn Layer, mode: BlendMode, context: &LayerContext) {
+        self.mode = mode;
+        layer.draw(self, context);
+        self.mode = BlendMode::Normal;
+    }
+
+    pub(crate) fn into_image(self) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        self.image
+    }
+}
+
+impl Layer for Background {
+    fn draw(&self, canvas: &mut Canvas, context: &LayerContext) {
+        let (width, height) = (context.width, context.height);
+        for y in 0..height {
+            for x in 0..width {
+                canvas.paint(x, y, self.color_at(x, y, width, height).to_rgba(255));
+            }
+        }
+    }
+}
+
+/// Repeating pattern over the background, sizes are in pixels of the `1.0` layout.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Pattern {
+    /// Stripes of the width with the gap between them, the angle in degrees turns them clockwise
+    Stripes { width: f32, gap: f32, angle: f32, color: RgbColor },
+    /// Grid of the dots with the radius, the spacing is the distance between their centers
+    Dots { radius: f32, spacing: f32, color: RgbColor },
+}
+
+impl Pattern {
+    /// Calculate the coverage of the pattern at the pixel center `(x, y)` in range `[0, 1]`.
+    fn coverage(&self, x: f32, y: f32, scale: f32) -> f32 {
+        let distance = match *self {
+            Pattern::Stripes { width, gap, angle, .. } => {
+                let (width, period) = (width * scale, (width + gap) * scale);
+                if period <= 0. {
+                    return 0.;
+                }
+
+                // distance to the center line of the nearest stripe
+                let (sin, cos) = angle.to_radians().sin_cos();
+                let u = (x * cos + y * sin).rem_euclid(period);
+                (u - width / 2.).abs().min(period - u + width / 2.) - width / 2.
+            }
+            Pattern::Dots { radius, spacing, .. } => {
+                let spacing = spacing * scale;
+                if spacing <= 0. {
+                    return 0.;
+                }
+
+                // the first dot is half of the spacing away from the corner
+                let (u, v) = ((x / spacing).fract() - 0.5, (y / spacing).fract() - 0.5);
+                (u * spacing).hypot(v * spacing) - radius * scale
+            }
+        };
+        (0.5 - distance).clamp(0., 1.)
+    }
+
+    fn color(&self) -> RgbColor {
+        match *self {
+            Pattern::Stripes { color, .. } | Pattern::Dots { color, .. } => color,
+        }
+    }
+}
+
+impl Layer for Pattern {
+    fn draw(&self, canvas: &mut Canvas, context: &LayerContext) {
+        let color = self.color();
+        for y in 0..context.height {
+            for x in 0..context.width {
+                let coverage = self.coverage(x as f32 + 0.5, y as f32 + 0.5, context.scale);
+                canvas.paint(x, y, color.to_rgba((coverage * 255.).round() as u8));
+            }
+        }
+    }
+}
+
+/// Image stretched over the avatar, e.g. a texture or a frame with transparent areas.
+#[derive(Debug, Clone)]
+pub struct Overlay {
+    image: Arc<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+}
+
+impl Overlay {
+    /// Construct new Overlay of the image.
+    pub fn new(image: ImageBuffer<Rgba<u8>, Vec<u8>>) -> Overlay {
+        Overlay { image: Arc::new(image) }
+    }
+}
+
+impl Overlay {
+    /// Sample the image stretched to the size at the pixel center `(x, y)` between the nearest pixels.
+    /// The colors are weighted by their alpha so the transparent pixels don't darken their neighbours.
+    fn sample(&self, x: u32, y: u32, width: u32, height: u32) -> Rgba<u8> {
+        let (iw, ih) = self.image.dimensions();
+        let sx = ((x as f32 + 0.5) * iw as f32 / width as f32 - 0.5).max(0.);
+        let sy = ((y as f32 + 0.5) * ih as f32 / height as f32 - 0.5).max(0.);
+        let (x0, y0) = ((sx as u32).min(iw - 1), (sy as u32).min(ih - 1));
+        let (x1, y1) = ((x0 + 1).min(iw - 1), (y0 + 1).min(ih - 1));
+        let (fx, fy) = (sx - x0 as f32, sy - y0 as f32);
+
+        // interpolate the premultiplied colors
+        let mut sum = [0.; 4];
+        for &(x, y, weight) in &[
+            (x0, y0, (1. - fx) * (1. - fy)),
+            (x1, y0, fx * (1. - fy)),
+            (x0, y1, (1. - fx) * fy),
+            (x1, y1, fx * fy),
+        ] {
+            let pixel = self.image.get_pixel(x, y).data;
+            let alpha = f32::from(pixel[3]) / 255.;
+            for (i, channel) in sum.iter_mut().take(3).enumerate() {
+                *channel += f32::from(pixel[i]) * alpha * weight;
+            }
+            sum[3] += alpha * weight;
+        }
+
+        let alpha = sum[3];
+        let mut data = [0; 4];
+        if alpha > 0. {
+            for (channel, value) in data.iter_mut().zip(&sum[..3]) {
+                *channel = (value / alpha).round().min(255.) as u8;
+            }
+        }
+        data[3] = (alpha * 255.).round() as u8;
+        Rgba { data }
+    }
+}
+
+impl Layer for Overlay {
+    fn draw(&self, canvas: &mut Canvas, context: &LayerContext) {
+        let (width, height) = (context.width, context.height);
+        if self.image.width() == 0 || self.image.height() == 0 {
+            return;
+        }
+
+        for y in 0..height {
+            for x in 0..width {
+                canvas.paint(x, y, self.sample(x, y, width, height));
+            }
+        }
+    }
+}
+
+/// Border drawn inside the edge of the avatar
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Border {
+    pub(crate) width: f32,
+    pub(crate) color: RgbColor,
+}
+
+/// Ring drawn around the avatar with a transparent gap between them
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Ring {
+    pub(crate) width: f32,
+    pub(crate) gap: f32,
+    pub(crate) color: RgbColor,
+}
+
+/// Anti-aliased band of the color along the outline of the shape,
+/// from the inset to the inset with the width in pixels.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Outline {
+    pub(crate) shape: Shape,
+    pub(crate) inset: f32,
+    pub(crate) width: f32,
+    pub(crate) color: RgbColor,
+    pub(crate) samples: u32,
+    /// The outer edge is cut with the avatar, so the band is drawn solid up to it
+    pub(crate) clipped: bool,
+}
+
+impl Layer for Outline {
+    fn draw(&self, canvas: &mut Canvas, context: &LayerContext) {
+        let (width, height) = (context.width, context.height);
+        for y in 0..height {
+            for x in 0..width {
+                let outer = self.shape.inset_coverage(x, y, width, height, self.inset, self.samples);
+                if outer > 0. {
+                    let outer = if self.clipped { 1. } else { outer };
+                    let inner = self.shape.inset_coverage(x, y, width, height, self.inset + self.width, self.samples);
+                    canvas.paint(x, y, self.color.to_rgba(((outer - inner) * 255.).round() as u8));
+                }
+            }
+        }
+    }
+}
+
+/// Text of the avatar with the layers of its effects under it.
+#[derive(Debug)]
+pub(crate) struct TextLayer {
+    pub(crate) mask: Mask,
+    pub(crate) effects: Effects,
+}
+
+impl Layer for TextLayer {
+    fn draw(&self, canvas: &mut Canvas, context: &LayerContext) {
+        let mut layers = self.effects.layers(&self.mask);
+        layers.push((context.font_color, self.mask.clone()));
+        for (color, mask) in &layers {
+            for (x, y, coverage) in mask.pixels() {
+                canvas.paint(x, y, color.to_rgba((coverage.min(1.) * 255.).round() as u8));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(background: &Background) -> LayerContext<'_> {
+        LayerContext { width: 4, height: 4, scale: 1., font_color: RgbColor::new(255, 255, 255), background }
+    }
+
+    #[test]
+    fn test_blend_modes() {
+        assert_eq!(BlendMode::Normal.blend(0.25, 0.5), 0.25);
+        assert_eq!(BlendMode::Multiply.blend(0.5, 0.5), 0.25);
+        assert_eq!(BlendMode::Screen.blend(0.5, 0.5), 0.75);
+        assert_eq!(BlendMode::Overlay.blend(0.5, 0.25), 0.25);
+        assert_eq!(BlendMode::Overlay.blend(0.5, 0.75), 0.75);
+    }
+
+    #[test]
+    fn test_canvas_paint() {
+        let background = Background::Solid(RgbColor::new(128, 128, 128));
+        let mut canvas = Canvas::new(4, 4, false);
+        canvas.draw(&background, BlendMode::Normal, &context(&background));
+        assert_eq!(canvas.image().get_pixel(0, 0).data, [128, 128, 128, 255]);
+
+        canvas.mode = BlendMode::Multiply;
+        canvas.paint(0, 0, Rgba([128, 255, 0, 255]));
+        assert_eq!(canvas.image().get_pixel(0, 0).data, [64, 128, 0, 255]);
+
+        canvas.mode = BlendMode::Screen;
+        canvas.paint(1, 0, Rgba([128, 0, 255, 128]));
+        assert_eq!(canvas.image().get_pixel(1, 0).data, [160, 128, 192, 255]);
+
+        // nothing under the layer keeps its own color
+        let mut canvas = Canvas::new(4, 4, false);
+        canvas.mode = BlendMode::Multiply;
+        canvas.paint(0, 0, Rgba([10, 20, 30, 255]));
+        assert_eq!(canvas.image().get_pixel(0, 0).data, [10, 20, 30, 255]);
+    }
+
+    #[test]
+    fn test_stripes() {
+        let stripes = Pattern::Stripes { width: 2., gap: 2., angle: 0., color: RgbColor::new(0, 0, 0) };
+        let covered: Vec<_> = (0..8).map(|x| stripes.coverage(x as f32 + 0.5, 0.5, 1.)).collect();
+        assert_eq!(covered, vec![1., 1., 0., 0., 1., 1., 0., 0.]);
+
+        // the stripes scale with the avatar
+        assert_eq!(stripes.coverage(3.5, 0.5, 2.), 1.);
+    }
+
+    #[test]
+    fn test_dots() {
+        let dots = Pattern::Dots { radius: 1., spacing: 4., color: RgbColor::new(0, 0, 0) };
+        assert_eq!(dots.coverage(2., 2., 1.), 1.);
+        assert_eq!(dots.coverage(6., 6., 1.), 1.);
+        assert_eq!(dots.coverage(0.5, 0.5, 1.), 0.);
+    }
+
+    #[test]
+    fn test_outline() {
+        let background = Background::Solid(RgbColor::new(0, 0, 0));
+        let context = LayerContext { width: 10, height: 10, ..context(&background) };
+        let outline = Outline {
+            shape: Shape::Square,
+            inset: 1.,
+            width: 2.,
+            color: RgbColor::new(255, 0, 0),
+            samples: 1,
+            clipped: false,
+        };
+        let mut canvas = Canvas::new(10, 10, false);
+        canvas.draw(&outline, BlendMode::Normal, &context);
+        let row: Vec<_> = (0..10).map(|x| canvas.image().get_pixel(x, 5).data[3]).collect();
+        assert_eq!(row, vec![0, 255, 255, 0, 0, 0, 0, 255, 255, 0]);
+
+        // the outer edge is anti-aliased unless the avatar cuts it
+        let circle = |clipped| {
+            let mut canvas = Canvas::new(10, 10, false);
+            canvas.draw(&Outline { shape: Shape::Circle, clipped, ..outline }, BlendMode::Normal, &context);
+            canvas.into_image()
+        };
+        let (soft, solid) = (circle(false), circle(true));
+        assert!(soft
+            .pixels()
+            .zip(solid.pixels())
+            .any(|(s, c)| s.data[3] > 0 && s.data[3] < 255 && c.data[3] == 255));
+    }
+
+    #[test]
+    fn test_overlay() {
+        let background = Background::Solid(RgbColor::new(0, 0, 0));
+        let overlay = Overlay::new(ImageBuffer::from_pixel(2, 2, Rgba([255, 0, 0, 255])));
+        let mut canvas = Canvas::new(4, 4, false);
+        canvas.draw(&overlay, BlendMode::Normal, &context(&background));
+        assert!(canvas.image().pixels().all(|p| p.data == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_overlay_sample() {
+        let mut image = ImageBuffer::new(2, 1);
+        image.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+        image.put_pixel(1, 0, Rgba([255, 255, 255, 255]));
+        let overlay = Overlay::new(image);
+        assert_eq!(overlay.sample(0, 0, 4, 1).data, [0, 0, 0, 255]);
+        assert_eq!(overlay.sample(1, 0, 4, 1).data, [64, 64, 64, 255]);
+        assert_eq!(overlay.sample(3, 0, 4, 1).data, [255, 255, 255, 255]);
+
+        // a transparent black pixel fades its opaque neighbour without darkening it
+        let mut image = ImageBuffer::new(2, 1);
+        image.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+        image.put_pixel(1, 0, Rgba([0, 0, 0, 0]));
+        let overlay = Overlay::new(image);
+        assert_eq!(overlay.sample(1, 0, 4, 1).data, [255, 255, 255, 191]);
+        assert_eq!(overlay.sample(2, 0, 4, 1).data, [255, 255, 255, 64]);
+        assert_eq!(overlay.sample(3, 0, 4, 1).data, [0, 0, 0, 0]);
+    }
+}
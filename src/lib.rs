@@ -13,7 +13,8 @@
 //! use initials::AvatarBuilder;
 //! 
 //! let image = AvatarBuilder::new("Anakin Skywalker")
-//!     .draw();
+//!     .draw()
+//!     .unwrap();
 //!
 //! ```
 //! This will import dynamic RGBA image.
@@ -23,7 +24,8 @@
 //! use initials::AvatarBuilder;
 //!
 //! let image = AvatarBuilder::new("Anakin Skywalker")
-//!     .draw();
+//!     .draw()
+//!     .unwrap();
 //!
 //! image.save("avatar.jpg").unwrap();
 //! ```
@@ -40,23 +42,50 @@
 //!   -  **length:** 2
 //!   -  **width:** 300
 //!   -  **height:** 300
-//!   -  **contrast_ratio:** 4.5
+//!   -  **contrast_ratio:** 4.5 (WCAG AA)
+//!   -  **contrast_algorithm:** WCAG 2.x
 //!   -  **font_color:** randomly generated
 //!   -  **background_color:** randomly generated
+//!   -  **shape:** Square
 //!
 //! ##### Manipulation
 //!
 //! |  method | description |
 //! |-----------|-------------|
-//! |  with_font(str) | Font file path(.ttf)  |
+//! |  with_font(str) | Font file path(.ttf, .otf, .ttc, .otc)  |
+//! |  with_font_bytes(`Vec<u8>` or `Arc<[u8]>`) | Font file data  |
+//! |  with_system_font(str, u16) | Installed font family and weight  |
+//! |  with_font_source(FontSource) | Font path, data, collection face or installed family  |
+//! |  with_font_fallbacks(`Vec<FontSource>`) | Fonts for the characters missing from the primary font |
 //! |  with_font_color(str)   | Font hex color code  |
 //! |  with_font_scale(f32)  | Uniform scale of the text |
+//! |  with_font_size(FontSize)  | Fixed scale or auto-fit of the text |
 //! |  with_background_color(str)  | Background hex color code  |
+//! |  with_background(Background)  | Solid color, linear or radial gradient  |
+//! |  with_random_gradient()  | Generate the random background as a gradient  |
 //! |  with_length(usize)  |  Font length |
+//! |  with_strategy(InitialsStrategy)  | Name parsing strategy for the initials |
+//! |  with_script(Script)  | Override the detected script of the name |
 //! |  with_height(u32)  | Image height  |
 //! |  with_width(u32)  | Image width  |
-//! |  with_contrast_ratio(u32)  | Contrast ratio for the randomly generated colors  |
+//! |  with_contrast_ratio(f32)  | Contrast ratio for the randomly generated colors  |
+//! |  with_contrast_algorithm(ContrastAlgorithm)  | WCAG 2.x contrast ratio or APCA lightness contrast  |
 //! |  with_blur(f32)  | Applied Gaussian Filter  |
+//! |  with_text_stroke(f32, str)  | Outline width and hex color of the text  |
+//! |  with_drop_shadow((f32, f32), f32, str)  | Shadow offset, blur and hex color of the text  |
+//! |  with_outer_glow(f32, str)  | Glow radius and hex color around the text  |
+//! |  with_pattern(Pattern, BlendMode)  | Stripes or dots under the text  |
+//! |  with_overlay(ImageBuffer, BlendMode)  | Image stretched over the avatar  |
+//! |  with_layer(Layer, LayerPosition, BlendMode)  | Custom layer below or above the text  |
+//! |  with_seed(u64)  | Seed for deterministic color generation  |
+//! |  with_deterministic_colors()  | Seed the colors from the full name  |
+//! |  with_shape(Shape)  | Outline of the avatar (`Square`, `Circle`, `RoundedRect`, `Squircle`)  |
+//! |  with_border(f32, str)  | Border width and hex color along the inside of the shape  |
+//! |  with_ring(f32, f32, str)  | Ring width, gap and hex color around the avatar  |
+//! |  with_badge(Badge)  | Status dot or notification count over a corner  |
+//! |  with_device_pixel_ratio(f32)  | Device pixels per layout pixel for HiDPI screens  |
+//! |  with_linear_blending(bool)  | Blend the text edges in linear light  |
+//! |  with_supersampling(u32)  | Samples per axis for the edges of the shape  |
 //! 
 //! ##### Example
 //! 
@@ -65,7 +94,7 @@
 //! use initials::{AvatarBuilder, AvatarResult};
 //!
 //! fn avatar() -> AvatarResult {
-//!	    AvatarBuilder::new("Anaking Skywalker")
+//!     AvatarBuilder::new("Anaking Skywalker")
 //!         .with_font_color("#000000")?
 //!         .with_background_color("#FAFAFA")?
 //!         .with_length(1)
@@ -73,7 +102,7 @@
 //!
 //! fn main() {
 //!     let avatar = avatar().unwrap();
-//!     let image = avatar.draw();
+//!     let image = avatar.draw().unwrap();
 //! }
 //!
 //! ```
@@ -86,26 +115,275 @@
 //!
 //! ```
 //! use initials::{AvatarBuilder, AvatarResult};
-//!	
+//!
 //! fn avatar_with_random_font() -> AvatarResult {
-//!	    AvatarBuilder::new("Lucky Seven")
+//!     AvatarBuilder::new("Lucky Seven")
 //!         .with_background_color("#FAFAFA")
 //! }
 //!
 //! fn avatar_with_random_background() -> AvatarResult {
-//!	    AvatarBuilder::new("Lucky Seven")
+//!     AvatarBuilder::new("Lucky Seven")
 //!         .with_font_color("#000000")
 //! }
 //!
 //! fn main() {
-//!     let img1 = avatar_with_random_background().unwrap().draw();
-//!     let img2 = avatar_with_random_font().unwrap().draw();
+//!     let img1 = avatar_with_random_background().unwrap().draw().unwrap();
+//!     let img2 = avatar_with_random_font().unwrap().draw().unwrap();
 //! }
 //!
 //! ```
 //! 
 //! - Means that you may fully customize the colors or unsetted colors will be automatically generated
-//! by providing clear and readable avatars according to the contrast ratio.
+//!   by providing clear and readable avatars according to the contrast ratio.
+//!   `draw` returns `Error::UnsatisfiableContrast` if no such colors exist, for example
+//!   with a mid-grey font and a contrast ratio of `21`.
+//!
+//! - Use `with_deterministic_colors` to derive the colors from the full name, so the same
+//!   user always gets the same avatar and the images can be cached.
+//!
+//! ```
+//! use initials::AvatarBuilder;
+//!
+//! let image = AvatarBuilder::new("Lucky Seven")
+//!     .with_deterministic_colors()
+//!     .unwrap()
+//!     .draw()
+//!     .unwrap();
+//! ```
+//!
+//! # Initials
+//!
+//! - By default the initials are the first letters of the words. Han, Hangul, Kana and Thai
+//!   names are detected and follow their own conventions, e.g. `王小明` → `王` and `김민준` → `민준`.
+//!   Use one of the strategies in the `strategy` module, or implement `InitialsStrategy`,
+//!   to parse other account fields.
+//!
+//! ```
+//! use initials::AvatarBuilder;
+//! use initials::strategy::{Email, SkipParticles};
+//!
+//! let avatar = AvatarBuilder::new("Ludwig van Beethoven")
+//!     .with_strategy(SkipParticles)
+//!     .unwrap();
+//! assert_eq!(avatar.name, "LB");
+//!
+//! let avatar = AvatarBuilder::new("jane.doe@example.com")
+//!     .with_strategy(Email)
+//!     .unwrap();
+//! assert_eq!(avatar.name, "JD");
+//! ```
+//!
+//! # Encoding
+//!
+//! - `encode` writes the avatar as PNG, JPEG, lossless WebP or multi-size ICO to any writer,
+//!   `encode_to_vec` returns the bytes. JPEG has no alpha channel, so the transparent pixels
+//!   are blended onto the background color instead of turning black.
+//!
+//! ```
+//! use initials::{AvatarBuilder, OutputFormat};
+//!
+//! let jpeg = AvatarBuilder::new("Lucky Seven")
+//!     .encode_to_vec(OutputFormat::Jpeg { quality: 90 })
+//!     .unwrap();
+//! ```
+//!
+//! - `draw_icons` draws the favicon and the app icons of a website from the avatar,
+//!   see `IconSet`.
+//!
+//! ```
+//! use initials::AvatarBuilder;
+//!
+//! let icons = AvatarBuilder::new("Lucky Seven").draw_icons().unwrap();
+//! for (name, data) in icons.files() {
+//!     // write the file to the web root
+//! }
+//! ```
+//!
+//! # SVG
+//!
+//! - `draw_svg` exports the same layout as an SVG document with a `<text>` element, and
+//!   `draw_svg_outlined` converts the glyphs to `<path>` data so no web font is needed.
+//! - The border, the ring and the badge are drawn as SVG elements and the text effects as
+//!   a filter, but the custom layers can only be rasterized and fail to draw in SVG.
+//!
+//! ```
+//! use initials::AvatarBuilder;
+//!
+//! let svg = AvatarBuilder::new("Lucky Seven")
+//!     .draw_svg_outlined()
+//!     .unwrap();
+//! ```
+//!
+//! # Text Effects
+//!
+//! - The stroke, the shadow and the glow are drawn under the text from its coverage,
+//!   so the text stays sharp. `with_blur` blurs the whole avatar instead.
+//!   SVG documents draw the effects with a filter.
+//!
+//! ```
+//! use initials::AvatarBuilder;
+//!
+//! let image = AvatarBuilder::new("Lucky Seven")
+//!     .with_font_color("#FFFFFF")
+//!     .and_then(|a| a.with_background_color("#336699"))
+//!     .and_then(|a| a.with_text_stroke(2., "#1E3A5F"))
+//!     .and_then(|a| a.with_drop_shadow((3., 3.), 4., "#000000"))
+//!     .unwrap()
+//!     .draw()
+//!     .unwrap();
+//! ```
+//!
+//! # Layers
+//!
+//! - The avatar is composited from a stack of layers: the background, the layers below
+//!   the text, the text with its effects and the layers above the text. Each layer is
+//!   blended with the `Normal`, `Multiply`, `Screen` or `Overlay` blend mode.
+//!   Implement `Layer` to draw custom designs.
+//!
+//! ```
+//! use initials::{AvatarBuilder, BlendMode, Pattern};
+//!
+//! let stripes = Pattern::Stripes {
+//!     width: 10.,
+//!     gap: 10.,
+//!     angle: 45.,
+//!     color: "#FFFFFF".parse().unwrap(),
+//! };
+//! let image = AvatarBuilder::new("Lucky Seven")
+//!     .with_pattern(stripes, BlendMode::Overlay)
+//!     .unwrap()
+//!     .draw()
+//!     .unwrap();
+//! ```
+//!
+//! # Gradients
+//!
+//! - Backgrounds can be linear or radial gradients. The random font color keeps the contrast
+//!   ratio against every color of the gradient under the text.
+//!
+//! ```
+//! use initials::{AvatarBuilder, Background};
+//!
+//! let image = AvatarBuilder::new("Lucky Seven")
+//!     .with_background(Background::LinearGradient {
+//!         angle: 135.,
+//!         stops: vec![(0., "#1E3A5F".parse().unwrap()), (1., "#336699".parse().unwrap())],
+//!     })
+//!     .unwrap()
+//!     .draw()
+//!     .unwrap();
+//! ```
+//!
+//! # Shapes
+//!
+//! - Avatars are square by default. Other shapes leave the outside area transparent,
+//!   so export them to a format with an alpha channel such as PNG.
+//!
+//! ```
+//! use initials::{AvatarBuilder, Shape};
+//!
+//! let image = AvatarBuilder::new("Lucky Seven")
+//!     .with_shape(Shape::Circle)
+//!     .unwrap()
+//!     .draw()
+//!     .unwrap();
+//! ```
+//!
+//! - `with_border` draws a band along the inside of the shape and `with_ring` draws
+//!   an outline around it with a transparent gap. Both follow the shape and shrink
+//!   the area the text is fitted into.
+//!
+//! ```
+//! use initials::{AvatarBuilder, Shape};
+//!
+//! let image = AvatarBuilder::new("Lucky Seven")
+//!     .with_shape(Shape::Circle)
+//!     .unwrap()
+//!     .with_ring(4., 2., "#336699")
+//!     .unwrap()
+//!     .draw()
+//!     .unwrap();
+//! ```
+//!
+//! # Badges
+//!
+//! - `with_badge` draws a status dot or the number of the unread notifications over
+//!   a corner of the avatar, with a transparent gap cut around it. Counts above 99 are
+//!   shown as `99+` and the zero count hides the badge.
+//!
+//! ```
+//! use initials::{AvatarBuilder, Badge, BadgeKind, BadgePosition, Shape};
+//!
+//! let image = AvatarBuilder::new("Lucky Seven")
+//!     .with_shape(Shape::Circle)
+//!     .unwrap()
+//!     .with_badge(Badge { position: BadgePosition::TopRight, kind: BadgeKind::Count(120), size: 80. })
+//!     .unwrap()
+//!     .draw()
+//!     .unwrap();
+//! ```
+//!
+//! # HiDPI
+//!
+//! - `with_device_pixel_ratio` scales the avatar for retina screens, `draw_densities`
+//!   draws every density at once with the same colors.
+//!
+//! ```
+//! use initials::AvatarBuilder;
+//!
+//! let images = AvatarBuilder::new("Lucky Seven")
+//!     .draw_densities(&[1.0, 2.0, 3.0])
+//!     .unwrap();
+//! assert_eq!(images[2].dimensions(), (900, 900));
+//! ```
+//!
+//! # Renderer
+//!
+//! - `Renderer` parses the fonts once and caches the rasterized glyphs. Share it between
+//!   threads to draw many avatars, `cargo bench` compares it with `AvatarBuilder::draw`.
+//!
+//! ```
+//! use initials::{AvatarBuilder, Renderer};
+//!
+//! let renderer = Renderer::new().unwrap();
+//! let image = renderer.draw(&AvatarBuilder::new("Lucky Seven")).unwrap();
+//! ```
+//!
+//! - `draw_batch` renders an iterator of avatars or names in the order of the input with
+//!   an error for each avatar. Enable the `parallel` feature to render them with `rayon`.
+//!
+//! ```
+//! use initials::Renderer;
+//!
+//! let renderer = Renderer::new().unwrap();
+//! for image in renderer.draw_batch(vec!["Lucky Seven", "Anakin Skywalker"]) {
+//!     let image = image.unwrap();
+//! }
+//! ```
+//!
+//! # Bundled Fonts
+//!
+//! - The default font is embedded at compile time and chosen with the cargo features
+//!   `font-hiragino` (default, covers CJK) or `font-dejavu` (DejaVu Sans, covers Latin,
+//!   Greek and Cyrillic). Hiragino is embedded if both are enabled.
+//! - Disable the default features to embed no font. Drawing then fails with
+//!   `Error::MissingFont` unless a font is given with `with_font` or the other font methods.
+//!
+//! ```toml
+//! [dependencies]
+//! initials = { version = "*", default-features = false, features = ["font-dejavu"] }
+//! ```
+//!
+//! # Complex Scripts
+//!
+//! - Enable the `shaping` feature to apply bidi reordering and OpenType shaping to the initials,
+//!   so Arabic letters join, Hebrew reads right-to-left and Devanagari matras are reordered.
+//!   The font still needs to have the glyphs of the script, see `with_font`.
+//!
+//! ```toml
+//! [dependencies]
+//! initials = { version = "*", features = ["shaping"] }
+//! ```
 
 #[macro_use]
 extern crate failure;
@@ -113,11 +391,49 @@ extern crate failure;
 extern crate rand;
 extern crate image;
 extern crate rusttype;
+extern crate ttf_parser;
+extern crate lru;
+extern crate png;
+extern crate image_webp;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+extern crate unicode_segmentation;
+#[cfg(feature = "shaping")]
+extern crate rustybuzz;
+#[cfg(feature = "shaping")]
+extern crate unicode_bidi;
 
+pub mod background;
+pub mod badge;
 pub mod color;
 pub mod avatar;
+pub mod batch;
+pub mod encode;
+mod effects;
 pub mod error;
+pub mod font;
+pub mod icons;
+pub mod layer;
+pub mod renderer;
+pub mod script;
+pub mod shape;
+#[cfg(feature = "shaping")]
+mod shaping;
+pub mod strategy;
+mod svg;
+mod system;
 
 pub use avatar::AvatarBuilder;
 pub use avatar::AvatarResult;
-pub use error::Error;
\ No newline at end of file
+pub use background::Background;
+pub use badge::{Badge, BadgeKind, BadgePosition};
+pub use color::ContrastAlgorithm;
+pub use encode::{OutputFormat, PngCompression};
+pub use error::Error;
+pub use font::{FontData, FontSize, FontSource};
+pub use icons::IconSet;
+pub use layer::{BlendMode, Layer, LayerPosition, Pattern};
+pub use renderer::Renderer;
+pub use script::Script;
+pub use shape::Shape;
+pub use strategy::InitialsStrategy;
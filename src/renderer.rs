@@ -0,0 +1,241 @@
+//! Renderer module draws many avatars with the fonts parsed once and the glyphs cached.
+use rusttype::PositionedGlyph;
+use image::{ImageBuffer, Rgba};
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, OnceLock};
+use avatar::AvatarBuilder;
+use batch::Batch;
+use error::Error;
+use font::{ChainFont, FontData, FontSource};
+
+/// Default number of rasterized glyphs kept in the cache
+const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+/// Long-lived renderer for high-throughput generation. It owns the parsed fonts and
+/// caches the rasterized glyphs, so it can be shared between threads and reused for every avatar.
+///
+/// ```
+/// use initials::{AvatarBuilder, Renderer};
+///
+/// let renderer = Renderer::new().unwrap();
+/// for name in &["Lucky Seven", "Anakin Skywalker"] {
+///     let avatar = AvatarBuilder::new(name);
+///     let image = renderer.draw(&avatar).unwrap();
+/// }
+/// ```
+pub struct Renderer {
+    /// Parsed primary font and the fallbacks
+    fonts: Vec<ChainFont<'static>>,
+    /// Rasterized glyphs of the fonts
+    glyphs: GlyphCache,
+}
+
+impl Renderer {
+    /// Construct new Renderer with the bundled font.
+    pub fn new() -> Result<Renderer, Error> {
+        let font = FontData::bundled().ok_or(Error::MissingFont)?;
+        Renderer::from_fonts(vec![font])
+    }
+
+    /// Construct new Renderer with the font and the fallbacks for the characters missing from it.
+    pub fn with_fonts(font: FontSource, fallbacks: Vec<FontSource>) -> Result<Renderer, Error> {
+        let fonts = ::std::iter::once(font)
+            .chain(fallbacks)
+            .map(FontSource::load)
+            .collect::<Result<_, _>>()?;
+        Renderer::from_fonts(fonts)
+    }
+
+    /// Change the number of rasterized glyphs kept in the cache.
+    /// Default to `1024`.
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Renderer {
+        self.glyphs = GlyphCache::new(capacity);
+        self
+    }
+
+    /// Draw the avatar with the fonts of the renderer, the fonts set on the avatar are not used.
+    /// Fails like `AvatarBuilder::draw` does.
+    pub fn draw(&self, avatar: &AvatarBuilder) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Error> {
+        avatar.render(&self.fonts, Some(&self.glyphs))
+    }
+
+    /// Draw the avatars or the names with the default settings in the order they are given,
+    /// see `Batch`. Enable the `parallel` feature to render them on all the cores.
+    pub fn draw_batch<I>(&self, avatars: I) -> Batch<'_, I::IntoIter>
+    where
+        I: IntoIterator,
+        I::Item: Into<AvatarBuilder>,
+    {
+        Batch::new(self, avatars.into_iter())
+    }
+
+    fn from_fonts(fonts: Vec<FontData>) -> Result<Renderer, Error> {
+        Ok(Renderer {
+            fonts: fonts.into_iter().map(ChainFont::owned).collect::<Result<_, _>>()?,
+            glyphs: GlyphCache::new(DEFAULT_CACHE_CAPACITY),
+        })
+    }
+}
+
+/// Rasterized coverage of a glyph, placed relative to the pixel of its origin.
+pub(crate) struct Coverage {
+    left: i32,
+    top: i32,
+    width: u32,
+    alpha: Vec<u8>,
+}
+
+impl Coverage {
+    /// Rasterize the glyph at its position.
+    pub(crate) fn rasterize(glyph: &PositionedGlyph) -> Coverage {
+        let bounding_box = match glyph.pixel_bounding_box() {
+            Some(bounding_box) => bounding_box,
+            None => return Coverage { left: 0, top: 0, width: 0, alpha: Vec::new() },
+        };
+
+        // the bounding box only depends on the fractional part of the position
+        let origin = glyph.position();
+        let width = bounding_box.width() as u32;
+        let mut alpha = vec![0; (width * bounding_box.height() as u32) as usize];
+        glyph.draw(|x, y, v| alpha[(y * width + x) as usize] = (v * 255.0) as u8);
+
+        Coverage {
+            left: bounding_box.min.x - origin.x.floor() as i32,
+            top: bounding_box.min.y - origin.y.floor() as i32,
+            width,
+            alpha,
+        }
+    }
+
+    /// Iterate over the covered pixels of the glyph with its origin at the pixel `(x, y)`.
+    pub(crate) fn pixels(&self, x: i32, y: i32) -> impl Iterator<Item = (i32, i32, u8)> + '_ {
+        let (left, top, width) = (x + self.left, y + self.top, self.width.max(1) as usize);
+        self.alpha
+            .iter()
+            .enumerate()
+            .map(move |(i, &alpha)| (left + (i % width) as i32, top + (i / width) as i32, alpha))
+    }
+}
+
+/// Key of the cached coverage: index of the font in the chain, glyph id, scale
+/// and the subpixel offset of the position.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+struct GlyphKey {
+    font: usize,
+    id: u16,
+    scale: (u32, u32),
+    offset: (u32, u32),
+}
+
+/// Slot of a cached glyph, filled by the first thread that needs its coverage.
+type Slot = Arc<OnceLock<Arc<Coverage>>>;
+
+/// Least recently used cache of the rasterized glyphs.
+pub(crate) struct GlyphCache(Mutex<LruCache<GlyphKey, Slot>>);
+
+impl GlyphCache {
+    fn new(capacity: usize) -> GlyphCache {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        GlyphCache(Mutex::new(LruCache::new(capacity)))
+    }
+
+    /// Get the coverage of the glyph of the font, rasterizing it on a miss.
+    /// The threads missing the same glyph wait for the first one to rasterize it.
+    pub(crate) fn coverage(&self, font: usize, glyph: &PositionedGlyph) -> Arc<Coverage> {
+        let (scale, position) = (glyph.scale(), glyph.position());
+        let key = GlyphKey {
+            font,
+            id: glyph.id().0,
+            scale: (scale.x.to_bits(), scale.y.to_bits()),
+            offset: (position.x.fract().to_bits(), position.y.fract().to_bits()),
+        };
+
+        let slot = self.lock().get_or_insert(key, Slot::default).clone();
+
+        // rasterize outside of the lock, so the threads drawing other glyphs are not blocked
+        slot.get_or_init(|| Arc::new(Coverage::rasterize(glyph))).clone()
+    }
+
+    fn lock(&self) -> ::std::sync::MutexGuard<'_, LruCache<GlyphKey, Slot>> {
+        // the cache stays consistent even if a thread panicked while holding it
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.lock().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use font::test_font;
+    use rusttype::{point, Scale};
+    use std::sync::Barrier;
+    use std::thread;
+
+    fn renderer() -> Renderer {
+        Renderer::with_fonts(test_font(), Vec::new()).unwrap()
+    }
+
+    fn avatar(name: &str) -> AvatarBuilder {
+        AvatarBuilder::new(name)
+            .with_font_source(test_font()).unwrap()
+            .with_font_color("#FFFFFF").unwrap()
+            .with_background_color("#000000").unwrap()
+    }
+
+    #[test]
+    fn test_renderer_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Renderer>();
+    }
+
+    #[test]
+    fn test_matches_builder() {
+        let renderer = renderer();
+        for name in &["Lucky Seven", "WA", "王小明"] {
+            let image = renderer.draw(&avatar(name)).unwrap();
+            assert_eq!(image.into_raw(), avatar(name).draw().unwrap().into_raw());
+        }
+
+        // drawn again from the cache
+        let image = renderer.draw(&avatar("Lucky Seven")).unwrap();
+        assert_eq!(image.into_raw(), avatar("Lucky Seven").draw().unwrap().into_raw());
+    }
+
+    #[test]
+    fn test_glyph_cache() {
+        let renderer = renderer().with_cache_capacity(2);
+        renderer.draw(&avatar("Lucky Seven")).unwrap();
+        assert_eq!(renderer.glyphs.len(), 2);
+
+        // least recently used glyphs are evicted
+        renderer.draw(&avatar("Anakin Skywalker")).unwrap();
+        assert_eq!(renderer.glyphs.len(), 2);
+    }
+
+    #[test]
+    fn test_concurrent_misses() {
+        let renderer = renderer();
+        let glyph = renderer.fonts[0].font.glyph('W').scaled(Scale::uniform(2000.)).positioned(point(0., 0.));
+        let barrier = Barrier::new(8);
+
+        // the threads missing the glyph at once share its single rasterization
+        let coverages: Vec<_> = thread::scope(|scope| {
+            let threads: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        renderer.glyphs.coverage(0, &glyph)
+                    })
+                })
+                .collect();
+            threads.into_iter().map(|thread| thread.join().unwrap()).collect()
+        });
+        assert!(coverages.iter().all(|coverage| Arc::ptr_eq(coverage, &coverages[0])));
+        assert_eq!(renderer.glyphs.len(), 1);
+    }
+}
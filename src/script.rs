@@ -0,0 +1,142 @@
+//! Script module detects the writing system of the name and applies its initials conventions.
+use unicode_segmentation::UnicodeSegmentation;
+use strategy::{InitialsStrategy, Words};
+
+/// Writing system of the name. Each script has its own convention for the initials
+/// and can be used as an `InitialsStrategy` to override the detection.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Script {
+    /// Chinese characters. Takes the single character of the surname: `"王小明"` → `"王"`
+    Han,
+    /// Korean. Takes the last two syllables of the given name: `"김민준"` → `"민준"`
+    Hangul,
+    /// Japanese kana. Takes the first character of the family name: `"やまだ たろう"` → `"や"`
+    Kana,
+    /// Thai. Takes the first consonant, skipping the leading vowels: `"เอกชัย"` → `"อ"`
+    Thai,
+    /// Any other script. Takes the first letter of each word.
+    Other,
+}
+
+impl Script {
+    /// Detect the script from the letters of the name. Names mixing scripts, e.g. `"김 Kim"`,
+    /// are `Other`, except for Kana mixed with Han which makes the name Japanese.
+    pub fn detect(name: &str) -> Script {
+        let mut letters = name.chars().filter(|c| c.is_alphabetic()).map(Script::of);
+        let first = match letters.next() {
+            Some(script) => script,
+            None => return Script::Other,
+        };
+
+        let mut detected = first;
+        for script in letters {
+            if detected.includes(script) {
+                continue;
+            }
+            match (detected, script) {
+                (Script::Han, Script::Kana) => detected = Script::Kana,
+                _ => return Script::Other,
+            }
+        }
+        detected
+    }
+
+    /// Check if the letters of the script are taken by the convention of this one.
+    fn includes(self, script: Script) -> bool {
+        self == script || (self == Script::Kana && script == Script::Han)
+    }
+
+    /// Find the script of the character.
+    fn of(c: char) -> Script {
+        match c as u32 {
+            0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => Script::Kana,
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xA960..=0xA97F | 0xAC00..=0xD7FF => Script::Hangul,
+            0x0E00..=0x0E7F => Script::Thai,
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x3134F => Script::Han,
+            _ => Script::Other,
+        }
+    }
+}
+
+impl InitialsStrategy for Script {
+    fn initials(&self, name: &str) -> String {
+        // names in east asian scripts are often written without spaces, so the graphemes
+        // of the script are taken from the whole name and the others are skipped.
+        // A script set as the override applies to the whole name if it has none of them
+        let letters: Vec<&str> = name.graphemes(true).filter(|g| !g.trim().is_empty()).collect();
+        let own: Vec<&str> = letters
+            .iter()
+            .cloned()
+            .filter(|g| g.chars().next().is_some_and(|c| self.includes(Script::of(c))))
+            .collect();
+        let graphemes = if own.is_empty() { letters } else { own };
+
+        match *self {
+            Script::Han | Script::Kana => graphemes.iter().take(1).cloned().collect(),
+            Script::Hangul => graphemes[graphemes.len().saturating_sub(2)..].concat(),
+            Script::Thai => name
+                .split_whitespace()
+                .next()
+                .and_then(|word| {
+                    // leading vowels are written before the consonant they follow
+                    word.graphemes(true)
+                        .find(|g| !g.starts_with(|c| ('\u{0E40}'..='\u{0E44}').contains(&c)))
+                })
+                .map(String::from)
+                .unwrap_or_default(),
+            Script::Other => Words.initials(name),
+        }
+    }
+}
+
+/// Detect the script of each name and apply its convention. Default strategy of the builder.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct DetectScript;
+
+impl InitialsStrategy for DetectScript {
+    fn initials(&self, name: &str) -> String {
+        Script::detect(name).initials(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Script::detect("Anakin Skywalker"), Script::Other);
+        assert_eq!(Script::detect("王小明"), Script::Han);
+        assert_eq!(Script::detect("김민준"), Script::Hangul);
+        assert_eq!(Script::detect("山田 はなこ"), Script::Kana);
+        assert_eq!(Script::detect("สมชาย ใจดี"), Script::Thai);
+        assert_eq!(Script::detect("Dev 王"), Script::Other);
+        assert_eq!(Script::detect("@王小明"), Script::Han);
+        assert_eq!(Script::detect("김 Kim"), Script::Other);
+        assert_eq!(Script::detect("\u{1100}\u{1161}\u{11A8} Kim"), Script::Other);
+    }
+
+    #[test]
+    fn test_script_initials() {
+        assert_eq!(Script::Han.initials("王小明"), "王");
+        assert_eq!(Script::Han.initials("欧阳 娜娜"), "欧");
+        assert_eq!(Script::Hangul.initials("김민준"), "민준");
+        assert_eq!(Script::Hangul.initials("김 민준"), "민준");
+        assert_eq!(Script::Hangul.initials("민"), "민");
+        assert_eq!(Script::Hangul.initials("김 Kim"), "김");
+        assert_eq!(Script::Han.initials("Dev 王"), "王");
+        assert_eq!(Script::Kana.initials("やまだ たろう"), "や");
+        assert_eq!(Script::Thai.initials("สมชาย ใจดี"), "ส");
+        assert_eq!(Script::Thai.initials("เอกชัย"), "อ");
+        assert_eq!(Script::Other.initials("Anakin Skywalker"), "AS");
+        assert_eq!(Script::Han.initials(""), "");
+        assert_eq!(Script::Hangul.initials(""), "");
+    }
+
+    #[test]
+    fn test_detect_script() {
+        assert_eq!(DetectScript.initials("王小明"), "王");
+        assert_eq!(DetectScript.initials("Lucky Seven"), "LS");
+        assert_eq!(DetectScript.initials("김 Kim"), "김K");
+    }
+}
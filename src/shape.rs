@@ -0,0 +1,229 @@
+//! Shape module that describes the outline of the avatar.
+
+/// Outline of the avatar. Pixels outside of the shape are left transparent.
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub enum Shape {
+    /// Plain rectangle covering the whole image
+    #[default]
+    Square,
+    /// Circle inscribed in the image
+    Circle,
+    /// Rectangle with rounded corners, radius is given in pixels
+    RoundedRect { radius: f32 },
+    /// Superellipse (`|x|^4 + |y|^4 = 1`) inscribed in the image
+    Squircle,
+}
+
+impl Shape {
+    /// Calculate the coverage of the pixel at `(x, y)` in range `[0, 1]`.
+    /// Pixels crossing the outline get partial coverage for anti-aliased edges.
+    pub fn coverage(&self, x: u32, y: u32, width: u32, height: u32) -> f32 {
+        self.supersampled_coverage(x, y, width, height, 1)
+    }
+
+    /// Calculate the coverage of the pixel at `(x, y)` averaged over `samples * samples`
+    /// subpixels, which follows the curved outlines more closely than a single sample.
+    pub fn supersampled_coverage(&self, x: u32, y: u32, width: u32, height: u32, samples: u32) -> f32 {
+        self.inset_coverage(x, y, width, height, 0., samples)
+    }
+
+    /// Calculate the supersampled coverage of the pixel at `(x, y)` by the shape shrunk
+    /// by the inset in pixels, e.g. the area inside a border.
+    pub fn inset_coverage(&self, x: u32, y: u32, width: u32, height: u32, inset: f32, samples: u32) -> f32 {
+        let samples = samples.max(1);
+        let (hw, hh) = (width as f32 / 2., height as f32 / 2.);
+        let step = 1. / samples as f32;
+
+        let mut coverage = 0.;
+        for i in 0..samples * samples {
+            // subpixel center relative to the image center
+            let px = (x as f32 + (i % samples) as f32 * step + step / 2. - hw).abs();
+            let py = (y as f32 + (i / samples) as f32 * step + step / 2. - hh).abs();
+            let distance = (self.distance_from_center(px, py, hw, hh) + inset) * samples as f32;
+            coverage += (0.5 - distance).clamp(0., 1.);
+        }
+        coverage / (samples * samples) as f32
+    }
+
+    /// Calculate the largest factor `s` so that a centered box of size
+    /// `s * box_width` by `s * box_height` fits inside the shape.
+    pub fn fit_box(&self, box_width: f32, box_height: f32, width: u32, height: u32) -> f32 {
+        self.fit_box_inset(box_width, box_height, width, height, 0.)
+    }
+
+    /// Calculate the largest factor like `fit_box` for the shape shrunk by the inset in pixels.
+    pub fn fit_box_inset(&self, box_width: f32, box_height: f32, width: u32, height: u32, inset: f32) -> f32 {
+        let (hw, hh) = (width as f32 / 2., height as f32 / 2.);
+
+        // the bounding rectangle is the upper limit for every shape
+        let (inner_width, inner_height) = ((width as f32 - 2. * inset).max(0.), (height as f32 - 2. * inset).max(0.));
+        let mut max = (inner_width / box_width).min(inner_height / box_height);
+        if !max.is_finite() || *self == Shape::Square {
+            return max;
+        }
+
+        // search for the factor that puts the corner of the box on the outline
+        let mut min = 0.;
+        for _ in 0..32 {
+            let mid = (min + max) / 2.;
+            if self.distance_from_center(mid * box_width / 2., mid * box_height / 2., hw, hh) + inset <= 0. {
+                min = mid;
+            } else {
+                max = mid;
+            }
+        }
+        min
+    }
+
+    /// Find the point of the outline on the diagonal from the center to the bottom right corner,
+    /// relative to the center of the image.
+    pub(crate) fn diagonal_outline(&self, width: u32, height: u32) -> (f32, f32) {
+        let (hw, hh) = (width as f32 / 2., height as f32 / 2.);
+        let (mut min, mut max) = (0., 1.);
+        for _ in 0..32 {
+            let mid = (min + max) / 2.;
+            if self.distance_from_center(mid * hw, mid * hh, hw, hh) <= 0. {
+                min = mid;
+            } else {
+                max = mid;
+            }
+        }
+        (min * hw, min * hh)
+    }
+
+    /// Scale the radius of the rounded rectangle by the factor,
+    /// the other shapes follow the size of the avatar on their own.
+    pub fn scaled(&self, factor: f32) -> Shape {
+        match *self {
+            Shape::RoundedRect { radius } => Shape::RoundedRect { radius: radius * factor },
+            shape => shape,
+        }
+    }
+
+    /// Signed distance from the point at `(px, py)` relative to the center
+    /// of the image with the given half extents, negative inside the shape and positive outside.
+    fn distance_from_center(&self, px: f32, py: f32, hw: f32, hh: f32) -> f32 {
+        match *self {
+            Shape::Square => rounded_rect_distance(px, py, hw, hh, 0.),
+            Shape::RoundedRect { radius } => rounded_rect_distance(px, py, hw, hh, radius),
+            Shape::Circle => (px * px + py * py).sqrt() - hw.min(hh),
+            Shape::Squircle => {
+                let r = hw.min(hh);
+                if r <= 0. {
+                    return 1.;
+                }
+
+                // F(x, y) = (x^4 + y^4)^(1/4) / r, distance is approximated by (F - 1) / |∇F|
+                let (u, v) = (px / r, py / r);
+                let f = (u.powi(4) + v.powi(4)).powf(0.25);
+                if f == 0. {
+                    return -r;
+                }
+                let (gx, gy) = (u.powi(3) / f.powi(3) / r, v.powi(3) / f.powi(3) / r);
+                (f - 1.) / (gx * gx + gy * gy).sqrt()
+            }
+        }
+    }
+}
+
+/// Signed distance to the rectangle with the given half extents and corner radius.
+fn rounded_rect_distance(px: f32, py: f32, hw: f32, hh: f32, radius: f32) -> f32 {
+    let r = radius.max(0.).min(hw.min(hh));
+    let (qx, qy) = (px - hw + r, py - hh + r);
+    let outside = (qx.max(0.).powi(2) + qy.max(0.).powi(2)).sqrt();
+    let inside = qx.max(qy).min(0.);
+    outside + inside - r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_square_covers_everything() {
+        assert_eq!(Shape::Square.coverage(0, 0, 10, 10), 1.);
+        assert_eq!(Shape::Square.coverage(9, 9, 10, 10), 1.);
+    }
+
+    #[test]
+    fn test_circle_coverage() {
+        let circle = Shape::Circle;
+        assert_eq!(circle.coverage(50, 50, 100, 100), 1.);
+        assert_eq!(circle.coverage(0, 0, 100, 100), 0.);
+        // pixel crossing the edge must be partially covered
+        let edge = circle.coverage(50, 0, 100, 101);
+        assert!(edge > 0. && edge < 1.);
+    }
+
+    #[test]
+    fn test_rounded_rect_coverage() {
+        let rounded = Shape::RoundedRect { radius: 20. };
+        assert_eq!(rounded.coverage(0, 0, 100, 100), 0.);
+        assert_eq!(rounded.coverage(0, 50, 100, 100), 1.);
+        assert_eq!(rounded.coverage(50, 99, 100, 100), 1.);
+    }
+
+    #[test]
+    fn test_fit_box() {
+        assert_eq!(Shape::Square.fit_box(2., 1., 100, 100), 50.);
+        // a square box inside the circle has the half diagonal of the radius
+        let circle = Shape::Circle.fit_box(1., 1., 100, 100);
+        assert!((circle - 50. * 2f32.sqrt()).abs() < 0.01);
+        assert!(Shape::Squircle.fit_box(1., 1., 100, 100) > circle);
+        let rounded = Shape::RoundedRect { radius: 20. }.fit_box(1., 1., 100, 100);
+        assert!(rounded > circle && rounded < 100.);
+    }
+
+    #[test]
+    fn test_inset() {
+        let circle = Shape::Circle;
+        assert_eq!(circle.inset_coverage(50, 2, 100, 100, 0., 1), 1.);
+        assert_eq!(circle.inset_coverage(50, 2, 100, 100, 5., 1), 0.);
+        assert_eq!(circle.inset_coverage(50, 6, 100, 100, 5., 1), 1.);
+
+        assert_eq!(Shape::Square.fit_box_inset(1., 1., 100, 100, 10.), 80.);
+        let fit = circle.fit_box_inset(1., 1., 100, 100, 10.);
+        assert!((fit - 40. * 2f32.sqrt()).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_diagonal_outline() {
+        let (x, y) = Shape::Square.diagonal_outline(100, 60);
+        assert!((x - 50.).abs() < 0.01 && (y - 30.).abs() < 0.01);
+        let (x, y) = Shape::Circle.diagonal_outline(100, 100);
+        assert!((x.hypot(y) - 50.).abs() < 0.01 && x == y);
+    }
+
+    #[test]
+    fn test_scaled() {
+        assert_eq!(Shape::RoundedRect { radius: 20. }.scaled(0.5), Shape::RoundedRect { radius: 10. });
+        assert_eq!(Shape::Circle.scaled(0.5), Shape::Circle);
+    }
+
+    #[test]
+    fn test_supersampled_coverage() {
+        let circle = Shape::Circle;
+        assert_eq!(circle.supersampled_coverage(50, 0, 100, 101, 1), circle.coverage(50, 0, 100, 101));
+        assert_eq!(circle.supersampled_coverage(50, 50, 100, 100, 4), 1.);
+        assert_eq!(circle.supersampled_coverage(0, 0, 100, 100, 4), 0.);
+
+        // the covered area of the circle converges to its real area
+        let area = |samples| {
+            (0..100 * 100)
+                .map(|i| circle.supersampled_coverage(i % 100, i / 100, 100, 100, samples))
+                .sum::<f32>()
+        };
+        let real = ::std::f32::consts::PI * 50. * 50.;
+        assert!((area(4) - real).abs() <= (area(1) - real).abs() + 0.5);
+        assert!((area(4) - real).abs() < 1.);
+    }
+
+    #[test]
+    fn test_squircle_coverage() {
+        let squircle = Shape::Squircle;
+        assert_eq!(squircle.coverage(50, 50, 100, 100), 1.);
+        assert_eq!(squircle.coverage(0, 0, 100, 100), 0.);
+        // squircle covers more than the circle near the corners
+        assert!(squircle.coverage(10, 10, 100, 100) > Shape::Circle.coverage(10, 10, 100, 100));
+    }
+}
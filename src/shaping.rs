@@ -0,0 +1,189 @@
+//! Shaping module applies bidi reordering and OpenType shaping to the initials,
+//! so Arabic, Hebrew, Indic and other complex scripts render with their proper forms.
+use std::ops::Range;
+use rusttype::{point, Font, GlyphId, Point, PositionedGlyph, Scale};
+use rustybuzz::{self, Direction, Face, GlyphInfo, GlyphPosition, UnicodeBuffer};
+use unicode_bidi::BidiInfo;
+use font::FontData;
+
+/// Split the text into the runs of the same direction in the visual order of the line.
+/// Returns the byte ranges of the runs, flagged if they're right-to-left.
+pub fn visual_runs(text: &str) -> Vec<(Range<usize>, bool)> {
+    let bidi = BidiInfo::new(text, None);
+    let mut runs = Vec::new();
+    for paragraph in &bidi.paragraphs {
+        let (levels, visual) = bidi.visual_runs(paragraph, paragraph.range.clone());
+        runs.extend(visual.into_iter().map(|run| {
+            let rtl = levels[run.start].is_rtl();
+            (run, rtl)
+        }));
+    }
+    runs
+}
+
+/// Shape the text of a single direction into glyphs in visual order, starting from the pen position `start`.
+/// Returns the glyphs with the total advance width, or `None` if the shaper can't read the font.
+pub fn shape<'a>(
+    font: &Font<'a>,
+    data: &FontData,
+    text: &str,
+    rtl: bool,
+    scale: Scale,
+    start: Point<f32>,
+) -> Option<(Vec<PositionedGlyph<'a>>, f32)> {
+    let face = Face::from_slice(data.data(), data.index())?;
+
+    // convert font units to pixels the same way rusttype scales the glyphs
+    let v_metrics = font.v_metrics_unscaled();
+    let height = v_metrics.ascent - v_metrics.descent;
+    let (factor_x, factor_y) = (scale.x / height, scale.y / height);
+
+    let mut glyphs = Vec::new();
+    let mut x = start.x;
+    for (info, position) in shape_run(&face, text, rtl) {
+        let glyph = font
+            .glyph(GlyphId(info.glyph_id as u16))
+            .scaled(scale)
+            .positioned(point(
+                x + position.x_offset as f32 * factor_x,
+                start.y - position.y_offset as f32 * factor_y,
+            ));
+        glyphs.push(glyph);
+        x += position.x_advance as f32 * factor_x;
+    }
+
+    Some((glyphs, x - start.x))
+}
+
+/// Shape the text in the direction, the glyphs of right-to-left text are returned in visual order.
+fn shape_run(face: &Face, text: &str, rtl: bool) -> Vec<(GlyphInfo, GlyphPosition)> {
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.set_direction(if rtl { Direction::RightToLeft } else { Direction::LeftToRight });
+
+    let output = rustybuzz::shape(face, &[], buffer);
+    output.glyph_infos().iter().copied().zip(output.glyph_positions().iter().copied()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use font::FontSource;
+
+    fn data() -> &'static [u8] {
+        include_bytes!("fonts/Hiragino_Sans_GB_W3.ttf")
+    }
+
+    /// Font with the Arabic and Hebrew glyphs, including the Arabic presentation forms
+    fn dejavu() -> &'static [u8] {
+        include_bytes!("../tests/fonts/DejaVuSans.ttf")
+    }
+
+    /// Font with the Devanagari letters of the tests as boxes and the `akhn` conjunct,
+    /// generated by tests/fonts/devanagari.py
+    fn devanagari(text: &str) -> Vec<u16> {
+        let face = Face::from_slice(include_bytes!("../tests/fonts/InitialsDevanagariTest.ttf"), 0).unwrap();
+        shape_runs(&face, text).iter().map(|&(info, _)| info.glyph_id as u16).collect()
+    }
+
+    /// Shape each directional run of the text in the visual order of the line,
+    /// the clusters are offset to point into the whole text.
+    fn shape_runs(face: &Face, text: &str) -> Vec<(GlyphInfo, GlyphPosition)> {
+        let mut shaped = Vec::new();
+        for (run, rtl) in visual_runs(text) {
+            for (mut info, position) in shape_run(face, &text[run.clone()], rtl) {
+                info.cluster += run.start as u32;
+                shaped.push((info, position));
+            }
+        }
+        shaped
+    }
+
+    fn font_data() -> FontData {
+        FontSource::Bytes(data().into()).load().unwrap()
+    }
+
+    /// Shape the text and describe the glyphs by their source clusters.
+    fn snapshot(text: &str) -> Vec<u32> {
+        let face = Face::from_slice(data(), 0).unwrap();
+        shape_runs(&face, text).iter().map(|&(info, _)| info.cluster).collect()
+    }
+
+    /// Shape the text with DejaVu Sans and return the glyphs in visual order.
+    fn glyphs(text: &str) -> Vec<u16> {
+        let face = Face::from_slice(dejavu(), 0).unwrap();
+        shape_runs(&face, text).iter().map(|&(info, _)| info.glyph_id as u16).collect()
+    }
+
+    /// Find the glyph of the character in DejaVu Sans, e.g. of a presentation form.
+    fn glyph(c: char) -> u16 {
+        let font = Font::try_from_bytes(dejavu()).unwrap();
+        let id = font.glyph(c).id().0;
+        assert_ne!(id, 0, "missing glyph for {:?}", c);
+        id
+    }
+
+    #[test]
+    fn test_latin_matches_layout() {
+        let font = Font::try_from_bytes(data()).unwrap();
+        let (shaped, width) = shape(&font, &font_data(), "AS", false, Scale::uniform(100.), point(0., 80.)).unwrap();
+        let laid_out: Vec<_> = font.layout("AS", Scale::uniform(100.), point(0., 80.)).collect();
+
+        assert_eq!(shaped.len(), laid_out.len());
+        for (s, l) in shaped.iter().zip(&laid_out) {
+            assert_eq!(s.id(), l.id());
+            assert!((s.position().x - l.position().x).abs() < 1.);
+        }
+        let last = laid_out.last().unwrap();
+        assert!((width - last.position().x - last.unpositioned().h_metrics().advance_width).abs() < 1.);
+    }
+
+    #[test]
+    fn test_rtl_snapshots() {
+        // shin lamed: right-to-left letters are laid out in reverse order
+        assert_eq!(snapshot("\u{05E9}\u{05DC}"), vec![2, 0]);
+        assert_eq!(glyphs("\u{05E9}\u{05DC}"), vec![glyph('\u{05DC}'), glyph('\u{05E9}')]);
+        // alef beh teh: arabic reverses too, see `test_arabic_joining` for the forms
+        assert_eq!(snapshot("\u{0627}\u{0628}\u{062A}"), vec![4, 2, 0]);
+        // the left-to-right run keeps its place before the right-to-left run
+        assert_eq!(snapshot("A \u{05E9}\u{05DC}"), vec![0, 1, 4, 2]);
+    }
+
+    #[test]
+    fn test_arabic_joining() {
+        // beh teh beh: the letters join into the final, medial and initial forms from the left
+        assert_eq!(
+            glyphs("\u{0628}\u{062A}\u{0628}"),
+            vec![glyph('\u{FE90}'), glyph('\u{FE98}'), glyph('\u{FE91}')]
+        );
+        // alef doesn't join to the left, so beh after it starts a new word form
+        assert_eq!(glyphs("\u{0627}\u{0628}"), vec![glyph('\u{0628}'), glyph('\u{0627}')]);
+        // a single letter keeps the isolated form
+        assert_eq!(glyphs("\u{0628}"), vec![glyph('\u{0628}')]);
+    }
+
+    #[test]
+    fn test_indic_forms() {
+        // glyphs of the test font: ka, ssa, virama, vowel sign i and the ka-ssa conjunct
+        let (ka, ssa, virama, i, kssa) = (1, 2, 3, 4, 5);
+        assert_eq!(devanagari("\u{0915}\u{0937}"), vec![ka, ssa]);
+        // ka + virama + ssa: the half ka joins the ssa into the conjunct
+        assert_eq!(devanagari("\u{0915}\u{094D}\u{0937}"), vec![kssa]);
+        // ka + vowel sign i: the pre-base matra is drawn before the consonant
+        assert_eq!(devanagari("\u{0915}\u{093F}"), vec![i, ka]);
+        // and before the whole conjunct
+        assert_eq!(devanagari("\u{0915}\u{094D}\u{0937}\u{093F}"), vec![i, kssa]);
+        // a virama at the end of the syllable stays
+        assert_eq!(devanagari("\u{0915}\u{094D}"), vec![ka, virama]);
+    }
+
+    #[test]
+    fn test_indic_clusters() {
+        // ka + vowel sign i: the pre-base matra stays in the syllable cluster
+        assert_eq!(snapshot("\u{0915}\u{093F}"), vec![0, 0]);
+        // ka + virama: the virama stays in the cluster of its consonant
+        assert_eq!(snapshot("\u{0915}\u{094D}"), vec![0, 0]);
+        // two syllables stay apart
+        assert_eq!(snapshot("\u{0915}\u{0916}"), vec![0, 3]);
+    }
+}
@@ -0,0 +1,185 @@
+//! Strategy module decides which characters of the name become the initials.
+use std::fmt::Debug;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Strategy to extract the initials from the raw name.
+/// Implement it to plug a custom name parsing into `AvatarBuilder::with_strategy`.
+pub trait InitialsStrategy: Debug + Send + Sync {
+    /// Extract the initials from the name. The result is uppercased by the builder.
+    fn initials(&self, name: &str) -> String;
+}
+
+/// Take the first letter of each word. Default strategy.
+///
+/// `"Ludwig van Beethoven"` → `"LVB"`
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Words;
+
+impl InitialsStrategy for Words {
+    fn initials(&self, name: &str) -> String {
+        first_graphemes(name.split_whitespace())
+    }
+}
+
+/// Take the first letter of the first and the last word.
+///
+/// `"John Ronald Reuel Tolkien"` → `"JT"`
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FirstLast;
+
+impl InitialsStrategy for FirstLast {
+    fn initials(&self, name: &str) -> String {
+        let words: Vec<&str> = name.split_whitespace().collect();
+        match words.len() {
+            0 | 1 => first_graphemes(words),
+            n => first_graphemes(vec![words[0], words[n - 1]]),
+        }
+    }
+}
+
+/// Skip the lowercase particles between the first and the last word.
+///
+/// `"Ludwig van Beethoven"` → `"LB"`
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SkipParticles;
+
+impl InitialsStrategy for SkipParticles {
+    fn initials(&self, name: &str) -> String {
+        let words: Vec<&str> = name.split_whitespace().collect();
+        let last = words.len().saturating_sub(1);
+        first_graphemes(words.iter().enumerate().filter_map(|(i, word)| {
+            let particle = word.chars().next().is_some_and(char::is_lowercase);
+            if particle && i != 0 && i != last {
+                None
+            } else {
+                Some(*word)
+            }
+        }))
+    }
+}
+
+/// Treat the hyphenated parts of the words as separate words.
+///
+/// `"Jean-Luc Picard"` → `"JLP"`
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SplitHyphens;
+
+impl InitialsStrategy for SplitHyphens {
+    fn initials(&self, name: &str) -> String {
+        first_graphemes(name.split(|c: char| c.is_whitespace() || c == '-'))
+    }
+}
+
+/// Take the words of the local-part of an email address.
+///
+/// `"jane.doe+news@example.com"` → `"JD"`
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Email;
+
+impl InitialsStrategy for Email {
+    fn initials(&self, name: &str) -> String {
+        let local = name.trim().split('@').next().unwrap_or("");
+        let local = local.split('+').next().unwrap_or("");
+        first_graphemes(local.split(&['.', '_', '-'][..]))
+    }
+}
+
+/// Split handles on separators, digits and camelCase humps.
+///
+/// `"@janeDoe_42"` → `"JD"`
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Username;
+
+impl InitialsStrategy for Username {
+    fn initials(&self, name: &str) -> String {
+        let handle = name.trim().trim_start_matches('@');
+
+        let mut words = Vec::new();
+        let mut start = None;
+        let mut previous_lowercase = false;
+        for (i, c) in handle.char_indices() {
+            if !c.is_alphabetic() {
+                if let Some(s) = start.take() {
+                    words.push(&handle[s..i]);
+                }
+                previous_lowercase = false;
+                continue;
+            }
+
+            // an uppercase letter after a lowercase one starts a new word
+            if c.is_uppercase() && previous_lowercase {
+                if let Some(s) = start.take() {
+                    words.push(&handle[s..i]);
+                }
+            }
+
+            if start.is_none() {
+                start = Some(i);
+            }
+            previous_lowercase = c.is_lowercase();
+        }
+
+        if let Some(s) = start {
+            words.push(&handle[s..]);
+        }
+
+        first_graphemes(words)
+    }
+}
+
+/// Concatenate the first grapheme clusters of the words, so emoji sequences
+/// and combining accents stay whole.
+fn first_graphemes<'a, I: IntoIterator<Item = &'a str>>(words: I) -> String {
+    words
+        .into_iter()
+        .filter_map(|word| word.graphemes(true).next())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words() {
+        assert_eq!(Words.initials("Ludwig van Beethoven"), "LvB");
+        assert_eq!(Words.initials("o'brien"), "o");
+        assert_eq!(Words.initials("  "), "");
+    }
+
+    #[test]
+    fn test_first_last() {
+        assert_eq!(FirstLast.initials("John Ronald Reuel Tolkien"), "JT");
+        assert_eq!(FirstLast.initials("Cher"), "C");
+        assert_eq!(FirstLast.initials(""), "");
+    }
+
+    #[test]
+    fn test_skip_particles() {
+        assert_eq!(SkipParticles.initials("Ludwig van Beethoven"), "LB");
+        assert_eq!(SkipParticles.initials("Charles de la Fontaine"), "CF");
+        assert_eq!(SkipParticles.initials("jane doe"), "jd");
+    }
+
+    #[test]
+    fn test_split_hyphens() {
+        assert_eq!(SplitHyphens.initials("Jean-Luc Picard"), "JLP");
+        assert_eq!(SplitHyphens.initials("Mary- Jane"), "MJ");
+    }
+
+    #[test]
+    fn test_email() {
+        assert_eq!(Email.initials("jane.doe@example.com"), "jd");
+        assert_eq!(Email.initials("jane_doe+news@example.com"), "jd");
+        assert_eq!(Email.initials("admin@example.com"), "a");
+    }
+
+    #[test]
+    fn test_username() {
+        assert_eq!(Username.initials("@jdoe"), "j");
+        assert_eq!(Username.initials("@janeDoe_42"), "jD");
+        assert_eq!(Username.initials("JaneDoe"), "JD");
+        assert_eq!(Username.initials("jane-doe.dev"), "jdd");
+        assert_eq!(Username.initials("HTMLParser"), "H");
+    }
+}
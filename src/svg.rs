@@ -0,0 +1,493 @@
+//! SVG module renders the avatar layout as a resolution-independent document.
+use rusttype::{Font, OutlineBuilder, PositionedGlyph, Scale};
+use background::Background;
+use badge::BadgeLayer;
+use color::RgbColor;
+use effects::Effects;
+use layer::{Border, Ring};
+use shape::Shape;
+
+/// Avatar drawn as an SVG document, the sizes are in pixels like the rasterized avatar.
+pub(crate) struct Document<'a> {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) shape: Shape,
+    pub(crate) background: &'a Background,
+    pub(crate) blur: Option<f32>,
+    pub(crate) border: Option<Border>,
+    pub(crate) ring: Option<Ring>,
+    pub(crate) effects: Effects,
+    /// `<text>` or `<path>` elements of the initials
+    pub(crate) text: String,
+    /// Badge laid out in its corner with the elements of its label
+    pub(crate) badge: Option<(BadgeLayer, String)>,
+}
+
+impl<'a> Document<'a> {
+    /// Draw the parts of the avatar in the order of the rasterized layers.
+    pub(crate) fn render(&self) -> String {
+        let (width, height) = (self.width, self.height);
+        let mut defs = String::new();
+
+        // the gradients use the same geometry as the rasterized background
+        let fill = match *self.background {
+            Background::Solid(color) => color.to_hex(),
+            Background::LinearGradient { angle, ref stops } => {
+                let ((x1, y1), (x2, y2)) = Background::line(angle, width, height);
+                defs.push_str(&format!(
+                    "<linearGradient id=\"background\" gradientUnits=\"userSpaceOnUse\" \
+                     x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\">{}</linearGradient>",
+                    num(x1),
+                    num(y1),
+                    num(x2),
+                    num(y2),
+                    gradient_stops(stops)
+                ));
+                String::from("url(#background)")
+            }
+            Background::RadialGradient { center, ref stops } => {
+                let ((cx, cy), r) = Background::circle(center, width, height);
+                defs.push_str(&format!(
+                    "<radialGradient id=\"background\" gradientUnits=\"userSpaceOnUse\" \
+                     cx=\"{}\" cy=\"{}\" r=\"{}\">{}</radialGradient>",
+                    num(cx),
+                    num(cy),
+                    num(r),
+                    gradient_stops(stops)
+                ));
+                String::from("url(#background)")
+            }
+        };
+
+        // the border lies inside the avatar, which is shrunk by the ring and its gap
+        let inset = self.ring.map_or(0., |ring| ring.width + ring.gap);
+        let mut avatar = shape_element(Shape::Square, width, height, 0., &paint(&fill));
+        if let Some(border) = self.border {
+            avatar.push_str(&outline(self.shape, width, height, inset, border.width, border.color));
+        }
+        match effects_filter(&self.effects, width, height) {
+            Some(filter) => {
+                defs.push_str(&filter);
+                avatar.push_str(&format!("<g filter=\"url(#effects)\">{}</g>", self.text));
+            }
+            None => avatar.push_str(&self.text),
+        }
+
+        // clip the text together with the background for the non-square shapes
+        if self.shape != Shape::Square || inset > 0. {
+            defs.push_str(&format!(
+                "<clipPath id=\"shape\">{}</clipPath>",
+                shape_element(self.shape, width, height, inset, &paint("#000000"))
+            ));
+            avatar = format!("<g clip-path=\"url(#shape)\">{}</g>", avatar);
+        }
+        if let Some(ring) = self.ring {
+            avatar.push_str(&outline(self.shape, width, height, 0., ring.width, ring.color));
+        }
+
+        // the gap around the badge is cut out of the avatar and the ring
+        if let Some((ref badge, ref label)) = self.badge {
+            defs.push_str(&format!(
+                "<mask id=\"badge\">{}{}</mask>",
+                shape_element(Shape::Square, width, height, 0., &paint("#FFFFFF")),
+                pill(badge, badge.gap(), &paint("#000000"))
+            ));
+            avatar = format!(
+                "<g mask=\"url(#badge)\">{}</g>{}{}",
+                avatar,
+                pill(badge, 0., &paint(&badge.color.to_hex())),
+                label
+            );
+        }
+
+        let mut group = String::new();
+        if let Some(b) = self.blur {
+            defs.push_str(&format!(
+                "<filter id=\"blur\"><feGaussianBlur stdDeviation=\"{}\"/></filter>",
+                num(b)
+            ));
+            group.push_str(" filter=\"url(#blur)\"");
+        }
+
+        if !defs.is_empty() {
+            defs = format!("<defs>{}</defs>", defs);
+        }
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\
+             {defs}<g{group}>{avatar}</g></svg>",
+            w = width,
+            h = height,
+            defs = defs,
+            group = group,
+            avatar = avatar,
+        )
+    }
+}
+
+/// Create a `<text>` element with the baseline starting at `(x, y)`.
+/// The font families are listed in order, so the client applies the same fallbacks.
+pub(crate) fn text(
+    text: &str,
+    x: f32,
+    y: f32,
+    font: &Font,
+    families: &[String],
+    scale: Scale,
+    color: RgbColor,
+) -> String {
+    // rusttype scales the glyphs by the line height while SVG expects the em size
+    let v_metrics = font.v_metrics_unscaled();
+    let font_size = scale.y * f32::from(font.units_per_em()) / (v_metrics.ascent - v_metrics.descent);
+
+    let mut family = String::new();
+    for name in families {
+        family.push_str(&format!("'{}', ", escape(name)));
+    }
+    family.push_str("sans-serif");
+
+    format!(
+        "<text x=\"{}\" y=\"{}\" font-family=\"{}\" font-size=\"{}\" fill=\"{}\">{}</text>",
+        num(x),
+        num(y),
+        family,
+        num(font_size),
+        color.to_hex(),
+        escape(text)
+    )
+}
+
+/// Create a single `<path>` element from the outlines of the positioned glyphs.
+pub(crate) fn outlines(glyphs: &[PositionedGlyph], color: RgbColor) -> String {
+    let mut path = PathData {
+        data: String::new(),
+        x: 0.,
+        y: 0.,
+    };
+    for glyph in glyphs {
+        // outlines are relative to the pixel bounding box of the glyph
+        if let Some(bounding_box) = glyph.pixel_bounding_box() {
+            path.x = bounding_box.min.x as f32;
+            path.y = bounding_box.min.y as f32;
+            glyph.build_outline(&mut path);
+        }
+    }
+
+    let data = path.data;
+    if data.is_empty() {
+        return data;
+    }
+
+    format!("<path d=\"{}\" fill=\"{}\"/>", data, color.to_hex())
+}
+
+/// Create the element that draws the shape over the avatar shrunk by the inset in pixels,
+/// the shape is offset the same way as the rasterized outlines.
+fn shape_element(shape: Shape, width: u32, height: u32, inset: f32, paint: &str) -> String {
+    let (hw, hh) = (width as f32 / 2., height as f32 / 2.);
+    let (iw, ih) = ((hw - inset).max(0.), (hh - inset).max(0.));
+    match shape {
+        Shape::Square => rect(hw - iw, hh - ih, 2. * iw, 2. * ih, 0., paint),
+        Shape::RoundedRect { radius } => {
+            let r = radius.max(0.).min(hw.min(hh));
+            rect(hw - iw, hh - ih, 2. * iw, 2. * ih, (r - inset).max(0.), paint)
+        }
+        Shape::Circle => format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {}/>",
+            num(hw),
+            num(hh),
+            num((hw.min(hh) - inset).max(0.)),
+            paint
+        ),
+        Shape::Squircle => {
+            // approximate the superellipse with a polygon
+            let r = (hw.min(hh) - inset).max(0.);
+            let steps = 128;
+            let mut data = String::new();
+            for i in 0..steps {
+                let angle = i as f32 / steps as f32 * 2. * ::std::f32::consts::PI;
+                let (sin, cos) = angle.sin_cos();
+                let x = hw + r * cos.signum() * cos.abs().sqrt();
+                let y = hh + r * sin.signum() * sin.abs().sqrt();
+                data.push_str(&format!("{}{} {}", if i == 0 { 'M' } else { 'L' }, num(x), num(y)));
+            }
+            format!("<path d=\"{}Z\" {}/>", data, paint)
+        }
+    }
+}
+
+/// Create a `<rect>` element, the position and the radius are left out when they're zero.
+fn rect(x: f32, y: f32, width: f32, height: f32, radius: f32, paint: &str) -> String {
+    let mut element = String::from("<rect");
+    if x != 0. || y != 0. {
+        element.push_str(&format!(" x=\"{}\" y=\"{}\"", num(x), num(y)));
+    }
+    element.push_str(&format!(" width=\"{}\" height=\"{}\"", num(width), num(height)));
+    if radius > 0. {
+        element.push_str(&format!(" rx=\"{r}\" ry=\"{r}\"", r = num(radius)));
+    }
+    format!("{} {}/>", element, paint)
+}
+
+/// Fill attribute of the elements.
+fn paint(fill: &str) -> String {
+    format!("fill=\"{}\"", fill)
+}
+
+/// Create the band of the color along the outline of the shape, from the inset to the inset
+/// with the width, as a stroke centered between them.
+fn outline(shape: Shape, width: u32, height: u32, inset: f32, band: f32, color: RgbColor) -> String {
+    let stroke = format!("fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"", color.to_hex(), num(band));
+    shape_element(shape, width, height, inset + band / 2., &stroke)
+}
+
+/// Create the pill of the badge grown by the pixels.
+fn pill(badge: &BadgeLayer, grow: f32, paint: &str) -> String {
+    let (width, height) = (badge.size.0 + 2. * grow, badge.size.1 + 2. * grow);
+    rect(badge.center.0 - width / 2., badge.center.1 - height / 2., width, height, height / 2., paint)
+}
+
+/// Create the filter drawing the glow, the shadow and the stroke under the text like the
+/// rasterized effects. The dilation of the filters is square, so the corners of the stroke
+/// and the glow are sharper than the rasterized ones.
+fn effects_filter(effects: &Effects, width: u32, height: u32) -> Option<String> {
+    if *effects == Effects::default() {
+        return None;
+    }
+
+    let mut primitives = String::new();
+    let mut merge = String::new();
+    let mut layer = |name: &str, coverage: String, color: RgbColor| {
+        primitives.push_str(&coverage);
+        primitives.push_str(&format!(
+            "<feFlood flood-color=\"{}\"/><feComposite in2=\"{n}-coverage\" operator=\"in\" result=\"{n}\"/>",
+            color.to_hex(),
+            n = name
+        ));
+        merge.push_str(&format!("<feMergeNode in=\"{}\"/>", name));
+    };
+
+    if let Some(glow) = effects.glow {
+        // half of the radius is solid and the other half fades out
+        let coverage = format!(
+            "<feMorphology in=\"SourceAlpha\" operator=\"dilate\" radius=\"{}\"/>\
+             <feGaussianBlur stdDeviation=\"{}\" result=\"glow-coverage\"/>",
+            num(glow.radius / 2.),
+            num(glow.radius / 4.)
+        );
+        layer("glow", coverage, glow.color);
+    }
+    if let Some(shadow) = effects.shadow {
+        let coverage = format!(
+            "<feOffset in=\"SourceAlpha\" dx=\"{}\" dy=\"{}\"/>\
+             <feGaussianBlur stdDeviation=\"{}\" result=\"shadow-coverage\"/>",
+            num(shadow.offset.0),
+            num(shadow.offset.1),
+            num(shadow.blur)
+        );
+        layer("shadow", coverage, shadow.color);
+    }
+    if let Some(stroke) = effects.stroke {
+        let coverage = format!(
+            "<feMorphology in=\"SourceAlpha\" operator=\"dilate\" radius=\"{}\" result=\"stroke-coverage\"/>",
+            num(stroke.width)
+        );
+        layer("stroke", coverage, stroke.color);
+    }
+
+    Some(format!(
+        "<filter id=\"effects\" filterUnits=\"userSpaceOnUse\" x=\"0\" y=\"0\" width=\"{}\" height=\"{}\">\
+         {}<feMerge>{}<feMergeNode in=\"SourceGraphic\"/></feMerge></filter>",
+        width, height, primitives, merge
+    ))
+}
+
+/// Path data built from the glyph outlines, translated by the offset.
+struct PathData {
+    data: String,
+    x: f32,
+    y: f32,
+}
+
+impl PathData {
+    fn point(&self, x: f32, y: f32) -> String {
+        format!("{} {}", num(x + self.x), num(y + self.y))
+    }
+}
+
+impl OutlineBuilder for PathData {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let p = self.point(x, y);
+        self.data.push_str(&format!("M{}", p));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.point(x, y);
+        self.data.push_str(&format!("L{}", p));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (p1, p) = (self.point(x1, y1), self.point(x, y));
+        self.data.push_str(&format!("Q{} {}", p1, p));
+    }
+
+    // cubic curves come from the CFF outlines of OpenType fonts
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (p1, p2, p) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        self.data.push_str(&format!("C{} {} {}", p1, p2, p));
+    }
+
+    fn close(&mut self) {
+        self.data.push('Z');
+    }
+}
+
+/// Escape the characters that are not allowed in XML text and attributes.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Create the `<stop>` elements of the gradient.
+fn gradient_stops(stops: &[(f32, RgbColor)]) -> String {
+    stops
+        .iter()
+        .map(|&(offset, color)| format!("<stop offset=\"{}\" stop-color=\"{}\"/>", num(offset), color.to_hex()))
+        .collect()
+}
+
+/// Format the number with at most two decimals.
+fn num(value: f32) -> String {
+    let formatted = format!("{:.2}", value);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    if trimmed == "-0" {
+        String::from("0")
+    } else {
+        String::from(trimmed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use effects::Stroke;
+
+    #[test]
+    fn test_num() {
+        assert_eq!(num(150.), "150");
+        assert_eq!(num(1.5), "1.5");
+        assert_eq!(num(0.126), "0.13");
+        assert_eq!(num(-0.001), "0");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("<A&B>"), "&lt;A&amp;B&gt;");
+    }
+
+    #[test]
+    fn test_path_data() {
+        let mut path = PathData { data: String::new(), x: 10., y: 20. };
+        path.move_to(0., 0.);
+        path.quad_to(1., 1., 2., 0.);
+        path.curve_to(0., 1., 1., 2., 3., 3.);
+        path.close();
+        assert_eq!(path.data, "M10 20Q11 21 12 20C10 21 11 22 13 23Z");
+    }
+
+    fn document(width: u32, height: u32, shape: Shape, background: &Background, blur: Option<f32>) -> Document<'_> {
+        Document {
+            width,
+            height,
+            shape,
+            background,
+            blur,
+            border: None,
+            ring: None,
+            effects: Effects::default(),
+            text: String::new(),
+            badge: None,
+        }
+    }
+
+    #[test]
+    fn test_document() {
+        let background = Background::Solid(RgbColor::new(250, 250, 250));
+        let svg = document(100, 80, Shape::Circle, &background, Some(1.5)).render();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"80\""));
+        assert!(svg.contains("<circle cx=\"50\" cy=\"40\" r=\"40\" fill=\"#000000\"/>"));
+        assert!(svg.contains("<rect width=\"100\" height=\"80\" fill=\"#FAFAFA\"/>"));
+        assert!(svg.contains("<feGaussianBlur stdDeviation=\"1.5\"/>"));
+        assert!(svg.ends_with("</g></svg>"));
+    }
+
+    #[test]
+    fn test_gradient_document() {
+        let stops = vec![(0., RgbColor::new(0, 0, 0)), (1., RgbColor::new(255, 255, 255))];
+        let background = Background::LinearGradient { angle: 90., stops: stops.clone() };
+        let svg = document(100, 80, Shape::Square, &background, None).render();
+        assert!(svg.contains("<linearGradient id=\"background\" gradientUnits=\"userSpaceOnUse\" x1=\"0\" y1=\"40\" x2=\"100\" y2=\"40\">"));
+        assert!(svg.contains("<stop offset=\"1\" stop-color=\"#FFFFFF\"/>"));
+        assert!(svg.contains("<rect width=\"100\" height=\"80\" fill=\"url(#background)\"/>"));
+
+        let background = Background::RadialGradient { center: (0.5, 0.5), stops };
+        let svg = document(60, 80, Shape::Square, &background, None).render();
+        assert!(svg.contains("<radialGradient id=\"background\" gradientUnits=\"userSpaceOnUse\" cx=\"30\" cy=\"40\" r=\"50\">"));
+    }
+
+    #[test]
+    fn test_inset_shapes() {
+        let fill = paint("#000000");
+        assert_eq!(shape_element(Shape::Square, 100, 80, 10., &fill), "<rect x=\"10\" y=\"10\" width=\"80\" height=\"60\" fill=\"#000000\"/>");
+        assert_eq!(
+            shape_element(Shape::RoundedRect { radius: 15. }, 100, 80, 10., &fill),
+            "<rect x=\"10\" y=\"10\" width=\"80\" height=\"60\" rx=\"5\" ry=\"5\" fill=\"#000000\"/>"
+        );
+        assert_eq!(shape_element(Shape::Circle, 100, 80, 10., &fill), "<circle cx=\"50\" cy=\"40\" r=\"30\" fill=\"#000000\"/>");
+    }
+
+    #[test]
+    fn test_decorations() {
+        let background = Background::Solid(RgbColor::new(250, 250, 250));
+        let badge = BadgeLayer {
+            center: (85., 85.),
+            size: (20., 20.),
+            color: RgbColor::new(0, 0, 255),
+            label: None,
+            samples: 1,
+        };
+        let svg = Document {
+            border: Some(Border { width: 10., color: RgbColor::new(255, 0, 0) }),
+            ring: Some(Ring { width: 6., gap: 4., color: RgbColor::new(0, 255, 0) }),
+            effects: Effects { stroke: Some(Stroke { width: 2., color: RgbColor::new(0, 0, 0) }), ..Effects::default() },
+            text: String::from("<path d=\"M0 0Z\"/>"),
+            badge: Some((badge, String::from("<path d=\"M1 1Z\"/>"))),
+            ..document(100, 100, Shape::Circle, &background, None)
+        }
+        .render();
+
+        // the avatar is clipped inside the ring and its gap, with the border inside it
+        assert!(svg.contains("<clipPath id=\"shape\"><circle cx=\"50\" cy=\"50\" r=\"40\" fill=\"#000000\"/></clipPath>"));
+        assert!(svg.contains("<circle cx=\"50\" cy=\"50\" r=\"35\" fill=\"none\" stroke=\"#FF0000\" stroke-width=\"10\"/>"));
+        assert!(svg.contains("</g><circle cx=\"50\" cy=\"50\" r=\"47\" fill=\"none\" stroke=\"#00FF00\" stroke-width=\"6\"/>"));
+
+        // the effects are drawn under the text
+        assert!(svg.contains("<feMorphology in=\"SourceAlpha\" operator=\"dilate\" radius=\"2\" result=\"stroke-coverage\"/>"));
+        assert!(svg.contains("<g filter=\"url(#effects)\"><path d=\"M0 0Z\"/></g>"));
+
+        // the gap is cut out of the avatar and the ring, then the badge is drawn over them
+        assert!(svg.contains("<rect x=\"72\" y=\"72\" width=\"26\" height=\"26\" rx=\"13\" ry=\"13\" fill=\"#000000\"/></mask>"));
+        assert!(svg.contains("<g mask=\"url(#badge)\">"));
+        assert!(svg.ends_with("<rect x=\"75\" y=\"75\" width=\"20\" height=\"20\" rx=\"10\" ry=\"10\" fill=\"#0000FF\"/><path d=\"M1 1Z\"/></g></svg>"));
+    }
+}
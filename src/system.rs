@@ -0,0 +1,254 @@
+//! System module looks up the fonts installed in the standard fontconfig directories.
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use ttf_parser::{name, os2, Style, Weight};
+use error::Error;
+use font;
+
+/// Directories scanned for the installed fonts, system-wide first.
+pub(crate) fn font_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![
+        PathBuf::from("/usr/share/fonts"),
+        PathBuf::from("/usr/local/share/fonts"),
+    ];
+
+    if let Some(data) = env::var_os("XDG_DATA_HOME") {
+        dirs.push(PathBuf::from(data).join("fonts"));
+    }
+    if let Some(home) = env::var_os("HOME") {
+        let home = PathBuf::from(home);
+        dirs.push(home.join(".local/share/fonts"));
+        dirs.push(home.join(".fonts"));
+    }
+
+    dirs
+}
+
+/// Find the face of the family with the closest weight, upright faces are preferred over italics.
+/// Returns the data of the font file with the index of the face, the other files are not read whole.
+pub(crate) fn find(dirs: &[PathBuf], family: &str, weight: u16) -> Result<(Arc<[u8]>, u32), Error> {
+    let faces = scan(dirs);
+    let mut best: Option<(u32, &FaceInfo)> = None;
+    for face in faces.iter().filter(|face| face.family.eq_ignore_ascii_case(family)) {
+        let penalty = if face.italic { 1000 } else { 0 };
+        let distance = (i32::from(face.weight) - i32::from(weight)).unsigned_abs() + penalty;
+        if best.is_none_or(|(closest, _)| distance < closest) {
+            best = Some((distance, face));
+        }
+    }
+
+    match best {
+        Some((_, face)) => Ok((Arc::from(fs::read(&face.path)?), face.index)),
+        None => Err(Error::FontNotFound { family: String::from(family) }),
+    }
+}
+
+/// Face of an installed font file, described by its naming and OS/2 tables.
+#[derive(Debug)]
+struct FaceInfo {
+    path: PathBuf,
+    index: u32,
+    family: String,
+    weight: u16,
+    italic: bool,
+}
+
+/// Faces found in the scanned directories, shared by the lookups
+type Faces = Arc<Vec<FaceInfo>>;
+
+/// Scan the directories for the faces, the result is cached for each list of directories,
+/// so the fonts installed after the first lookup are not found until the process restarts.
+fn scan(dirs: &[PathBuf]) -> Faces {
+    static SCANS: OnceLock<Mutex<HashMap<Vec<PathBuf>, Faces>>> = OnceLock::new();
+
+    let mut scans = SCANS.get_or_init(Default::default).lock().unwrap_or_else(PoisonError::into_inner);
+    scans
+        .entry(dirs.to_vec())
+        .or_insert_with(|| {
+            let mut files = Vec::new();
+            for dir in dirs {
+                collect(dir, &mut files);
+            }
+            files.sort();
+
+            // unreadable files are skipped like fontconfig does
+            let faces = files.into_iter().flat_map(|file| read_faces(&file).unwrap_or_default());
+            Arc::new(faces.collect())
+        })
+        .clone()
+}
+
+/// Read the faces of the font file, only the header and the tables describing the faces
+/// are read instead of the whole file.
+fn read_faces(path: &Path) -> io::Result<Vec<FaceInfo>> {
+    let mut file = File::open(path)?;
+
+    // collections list the offsets of their faces after the header
+    let header = read_at(&mut file, 0, 12)?;
+    let offsets = if header.starts_with(b"ttcf") {
+        let count = u32_at(&header, 8);
+        let offsets = read_at(&mut file, 12, u64::from(count) * 4)?;
+        offsets.chunks(4).map(|offset| u32_at(offset, 0)).collect()
+    } else {
+        vec![0]
+    };
+
+    let mut faces = Vec::new();
+    for (index, offset) in offsets.into_iter().enumerate() {
+        if let Some(face) = read_face(&mut file, offset)? {
+            faces.push(FaceInfo { path: path.to_path_buf(), index: index as u32, ..face });
+        }
+    }
+    Ok(faces)
+}
+
+/// Read the family, the weight and the style of the face at the offset of the file.
+/// Returns `None` for the faces without a family name.
+fn read_face(file: &mut File, offset: u32) -> io::Result<Option<FaceInfo>> {
+    let header = read_at(file, u64::from(offset), 12)?;
+    let count = u16::from_be_bytes([header[4], header[5]]);
+    let records = read_at(file, u64::from(offset) + 12, u64::from(count) * 16)?;
+
+    let mut table = |tag: &[u8; 4], limit: u32| -> io::Result<Vec<u8>> {
+        match records.chunks(16).find(|record| &record[..4] == tag) {
+            Some(record) => read_at(file, u64::from(u32_at(record, 8)), u64::from(u32_at(record, 12).min(limit))),
+            None => Ok(Vec::new()),
+        }
+    };
+    let name = table(b"name", u32::MAX)?;
+    let os2 = table(b"OS/2", u32::MAX)?;
+    let post = table(b"post", 8)?;
+
+    let family = match name::Table::parse(&name).and_then(|table| font::family_name(table.names)) {
+        Some(family) => family,
+        None => return Ok(None),
+    };
+    let os2 = os2::Table::parse(&os2);
+    let italic_angle = if post.len() == 8 { u32_at(&post, 4) as i32 } else { 0 };
+
+    Ok(Some(FaceInfo {
+        path: PathBuf::new(),
+        index: 0,
+        family,
+        weight: os2.map_or(Weight::Normal, |os2| os2.weight()).to_number(),
+        italic: os2.is_some_and(|os2| os2.style() == Style::Italic) || italic_angle != 0,
+    }))
+}
+
+/// Read the bytes at the offset of the file, fails if the file ends before them.
+fn read_at(file: &mut File, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data = Vec::new();
+    file.take(len).read_to_end(&mut data)?;
+    if (data.len() as u64) < len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated font file"));
+    }
+    Ok(data)
+}
+
+/// Read the big endian number at the offset.
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// Collect the font files of the directory recursively.
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(t) if t.is_dir() => collect(&path, files),
+            Ok(_) if is_font(&path) => files.push(path),
+            _ => {}
+        }
+    }
+}
+
+/// Check the extension of the font file.
+fn is_font(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ["ttf", "otf", "ttc", "otc"].iter().any(|e| extension.eq_ignore_ascii_case(e))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Directory of a single test, removed with everything in it when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let dir = env::temp_dir().join(format!("initials-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_is_font() {
+        assert!(is_font(Path::new("/usr/share/fonts/Inter.OTF")));
+        assert!(is_font(Path::new("NotoSansCJK.ttc")));
+        assert!(!is_font(Path::new("fonts.conf")));
+    }
+
+    #[test]
+    fn test_find() {
+        let data = include_bytes!("../tests/fonts/DejaVuSans.ttf");
+        let family = font::family_name(ttf_parser::Face::parse(data, 0).unwrap().names()).unwrap();
+
+        let dir = TempDir::new("system-fonts");
+        let dirs = vec![dir.0.clone()];
+        fs::create_dir_all(dirs[0].join("truetype")).unwrap();
+        fs::write(dirs[0].join("truetype/font.ttf"), &data[..]).unwrap();
+
+        let (found, index) = find(&dirs, &family.to_uppercase(), 700).unwrap();
+        assert_eq!(&found[..], &data[..]);
+        assert_eq!(index, 0);
+
+        match find(&dirs, "No Such Family", 400) {
+            Err(Error::FontNotFound { ref family }) if family == "No Such Family" => {}
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_read_faces() {
+        let data = include_bytes!("../tests/fonts/DejaVuSans.ttf");
+        let temp = TempDir::new("system-faces");
+        let dir = &temp.0;
+        fs::write(dir.join("DejaVuSans.ttf"), &data[..]).unwrap();
+        fs::write(dir.join("truncated.ttf"), &data[..100]).unwrap();
+
+        let faces = read_faces(&dir.join("DejaVuSans.ttf")).unwrap();
+        assert_eq!(faces.len(), 1);
+        assert_eq!((faces[0].family.as_str(), faces[0].index), ("DejaVu Sans", 0));
+        assert_eq!((faces[0].weight, faces[0].italic), (400, false));
+        assert!(read_faces(&dir.join("truncated.ttf")).is_err());
+
+        // the scan is cached, so the fonts added later are not seen
+        let dirs = vec![dir.clone()];
+        let faces = scan(&dirs);
+        assert_eq!(faces.len(), 1);
+        fs::write(dir.join("copy.ttf"), &data[..]).unwrap();
+        assert!(Arc::ptr_eq(&faces, &scan(&dirs)));
+    }
+}
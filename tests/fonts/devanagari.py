@@ -0,0 +1,151 @@
+#!/usr/bin/env python3
+"""Generate InitialsDevanagariTest.ttf, the Devanagari font of the shaping tests.
+
+The glyphs are plain boxes, only the glyph ids matter to the tests:
+
+    1 ka, 2 ssa, 3 virama, 4 vowel sign i, 5 the ka-virama-ssa conjunct
+
+The GSUB table of the `dev2` script forms the conjunct with an `akhn` ligature,
+the pre-base vowel sign is reordered by the shaper itself.
+
+Run it from the repository root with only the standard library:
+
+    python3 tests/fonts/devanagari.py
+
+The generated font is released under the license of this crate.
+"""
+import struct
+
+FAMILY = "Initials Devanagari Test"
+OUTPUT = "tests/fonts/InitialsDevanagariTest.ttf"
+
+# character to glyph id, the conjunct has no character
+CMAP = {0x0915: 1, 0x0937: 2, 0x094D: 3, 0x093F: 4}
+KA, SSA, VIRAMA, KSSA = 1, 2, 3, 5
+NUM_GLYPHS = 6
+ADVANCE = 600
+
+
+def box_glyph(x_min, y_min, x_max, y_max):
+    points = [(x_min, y_min), (x_min, y_max), (x_max, y_max), (x_max, y_min)]
+    data = struct.pack(">hhhhh", 1, x_min, y_min, x_max, y_max)
+    data += struct.pack(">HH", len(points) - 1, 0)
+    data += bytes([0x01] * len(points))
+    deltas = [(x - px, y - py) for (x, y), (px, py) in zip(points, [(0, 0)] + points)]
+    data += b"".join(struct.pack(">h", dx) for dx, _ in deltas)
+    data += b"".join(struct.pack(">h", dy) for _, dy in deltas)
+    return data
+
+
+def glyf_loca():
+    glyphs = [box_glyph(50, 0, ADVANCE - 50, 700) for _ in range(NUM_GLYPHS)]
+    glyf, loca = b"", []
+    for glyph in glyphs:
+        glyph += b"\0" * (len(glyph) % 2)
+        loca.append(len(glyf) // 2)
+        glyf += glyph
+    loca.append(len(glyf) // 2)
+    return glyf, b"".join(struct.pack(">H", offset) for offset in loca)
+
+
+def cmap():
+    codes = sorted(CMAP) + [0xFFFF]
+    seg_count = len(codes)
+    search_range = 2 * 2 ** (seg_count.bit_length() - 1)
+    entry_selector = seg_count.bit_length() - 1
+    deltas = [(CMAP[code] - code) % 0x10000 for code in codes[:-1]] + [1]
+
+    subtable = struct.pack(">HHHH", seg_count * 2, search_range, entry_selector, seg_count * 2 - search_range)
+    subtable += b"".join(struct.pack(">H", code) for code in codes) + b"\0\0"
+    subtable += b"".join(struct.pack(">H", code) for code in codes)
+    subtable += b"".join(struct.pack(">H", delta) for delta in deltas)
+    subtable += b"\0\0" * seg_count
+    subtable = struct.pack(">HHH", 4, len(subtable) + 6, 0) + subtable
+    return struct.pack(">HHHHI", 0, 1, 3, 1, 12) + subtable
+
+
+def gsub():
+    # ligature of ka + virama + ssa into the conjunct
+    ligature = struct.pack(">HHHH", KSSA, 3, VIRAMA, SSA)
+    ligature_set = struct.pack(">HH", 1, 4) + ligature
+    coverage = struct.pack(">HHH", 1, 1, KA)
+    subtable = struct.pack(">HHHH", 1, 8, 1, 8 + len(coverage)) + coverage + ligature_set
+    lookup = struct.pack(">HHHH", 4, 0, 1, 8) + subtable
+    lookup_list = struct.pack(">HH", 1, 4) + lookup
+
+    feature = struct.pack(">HHH", 0, 1, 0)
+    feature_list = struct.pack(">H4sH", 1, b"akhn", 8) + feature
+
+    lang_sys = struct.pack(">HHHH", 0, 0xFFFF, 1, 0)
+    script = struct.pack(">HH", 4, 0) + lang_sys
+    script_list = struct.pack(">H4sH", 1, b"dev2", 8) + script
+
+    offset = 10
+    header = struct.pack(
+        ">HHHHH",
+        1,
+        0,
+        offset,
+        offset + len(script_list),
+        offset + len(script_list) + len(feature_list),
+    )
+    return header + script_list + feature_list + lookup_list
+
+
+def name():
+    names = [(1, FAMILY), (2, "Regular"), (4, FAMILY), (6, FAMILY.replace(" ", ""))]
+    strings, records = b"", b""
+    for name_id, text in names:
+        encoded = text.encode("utf-16-be")
+        records += struct.pack(">HHHHHH", 3, 1, 0x409, name_id, len(encoded), len(strings))
+        strings += encoded
+    return struct.pack(">HHH", 0, len(names), 6 + len(records)) + records + strings
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(">%dI" % (len(data) // 4), data)) & 0xFFFFFFFF
+
+
+def build():
+    glyf, loca = glyf_loca()
+    tables = {
+        b"GSUB": gsub(),
+        b"cmap": cmap(),
+        b"glyf": glyf,
+        b"head": struct.pack(
+            ">IIIIHHqqhhhhHHhhh",
+            0x00010000, 0x00010000, 0, 0x5F0F3CF5, 0x000B, 1000, 0, 0,
+            50, 0, ADVANCE - 50, 700, 0, 8, 2, 0, 0,
+        ),
+        b"hhea": struct.pack(
+            ">IhhhHhhhhhh8xhH",
+            0x00010000, 800, -200, 0, ADVANCE, 50, 50, ADVANCE - 50, 1, 0, 0, 0, NUM_GLYPHS,
+        ),
+        b"hmtx": struct.pack(">hh", ADVANCE, 50) * NUM_GLYPHS,
+        b"loca": loca,
+        b"maxp": struct.pack(">IHHHHHHHHHHHHHH", 0x00010000, NUM_GLYPHS, 4, 1, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0),
+        b"name": name(),
+    }
+
+    count = len(tables)
+    search_range = 16 * 2 ** (count.bit_length() - 1)
+    header = struct.pack(">IHHHH", 0x00010000, count, search_range, count.bit_length() - 1, count * 16 - search_range)
+
+    records, data = b"", b""
+    offset = len(header) + 16 * count
+    for tag in sorted(tables):
+        table = tables[tag]
+        records += struct.pack(">4sIII", tag, checksum(table), offset + len(data), len(table))
+        data += table + b"\0" * (-len(table) % 4)
+
+    font = bytearray(header + records + data)
+    # the checksum adjustment of the head table makes the whole font sum to the magic number
+    head = offset + sum(len(tables[tag]) + (-len(tables[tag]) % 4) for tag in sorted(tables) if tag < b"head")
+    struct.pack_into(">I", font, head + 8, (0xB1B0AFBA - checksum(bytes(font))) & 0xFFFFFFFF)
+    return bytes(font)
+
+
+if __name__ == "__main__":
+    with open(OUTPUT, "wb") as output:
+        output.write(build())